@@ -0,0 +1,11 @@
+#!/bin/sh
+# Run the std_ex tests with each static level feature, as the level compiled in changes which
+# logging calls exist at all. Extra arguments are passed to cargo, e.g. --offline.
+set -eu
+
+for level in off fatal error warn info debug trace; do
+    echo "== max_level_$level"
+    cargo test -p std_ex --features "max_level_$level" "$@"
+    echo "== release_max_level_$level"
+    cargo test -p std_ex --release --features "release_max_level_$level" "$@"
+done
@@ -5,15 +5,16 @@ use std_ex::{
 
 pub fn main() {
     let log = Log::new_ex().set_log_level(LogLevel::Trace).done();
+    let nested = format!(
+        "{}{}",
+        term::Colour::Green.paint("deep nested formats"),
+        term::Colour::Blue.paint(" everywhere")
+    );
     log.trace(format!(
         "{},{},{}",
         term::Colour::Magenta.paint("Trace log"),
         term::Colour::Yellow.paint(" with some colour"),
-        format!(
-            "{}{}",
-            term::Colour::Green.paint("deep nested formats"),
-            term::Colour::Blue.paint(" everywhere")
-        )
+        nested
     ));
     log.debug("Debug log");
     log.info("Info log");
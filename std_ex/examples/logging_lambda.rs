@@ -0,0 +1,18 @@
+use std_ex::log::{self, Format, Log, LogLevel};
+
+pub fn main() {
+    // Inside aws lambda, AWS_LAMBDA_LOG_FORMAT=JSON selects Format::Json automatically
+    let log = Log::new_ex()
+        .set_log_level(LogLevel::Trace)
+        .set_format(Format::Json)
+        .done();
+
+    for request_id in ["8476a536-e9f4-11e8-9739-2dfe598c3fcd", "a5d2c3f1-0d3a-4b2b"] {
+        let invocation = log.child_with_request_id(request_id);
+        invocation.info("Invocation started");
+        invocation.warnf("Invocation data", &Vec::from([1, 2, 3]));
+    }
+
+    log::set_request_id("0b1c3f7e-global");
+    log::info("Global logger with request id");
+}
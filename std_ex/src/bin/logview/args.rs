@@ -0,0 +1,176 @@
+use crate::view::Filter;
+use std::path::PathBuf;
+use std_ex::chrono::{DateTime, Utc};
+use std_ex::term::ColourPolicy;
+
+pub const USAGE: &str = "\
+Pretty print and filter JSON Lines logs, as written by std_ex::log's Format::Json.
+
+Usage: logview [OPTIONS] [FILE]...
+
+Reads each FILE in turn, or stdin when there are none or FILE is `-`.
+Lines which aren't JSON objects are shown as is, unless a filter is set.
+
+Options:
+  -l, --level LEVEL        Show records at LEVEL and above, e.g. warn
+  -t, --target TARGET      Show records from TARGET, or a target nested beneath it, may be repeated
+  -F, --field KEY=VALUE    Show records where the field KEY is VALUE, may be repeated
+      --since TIME         Show records at or after TIME, in RFC 3339, e.g. 2024-01-02T03:04:05Z
+      --until TIME         Show records at or before TIME, in RFC 3339
+  -f, --follow             Keep reading files as they grow
+      --color WHEN         Colour the level, auto, always or never [default: auto]
+  -h, --help               Show this help";
+
+/// What to do, from the command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    View(Args),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// Files to read, empty for stdin.
+    pub files: Vec<PathBuf>,
+    pub filter: Filter,
+    pub follow: bool,
+    pub colour_policy: ColourPolicy,
+}
+
+/// Parse the arguments, without the program name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    let mut only_files = false;
+    while let Some(arg) = args.next() {
+        if only_files || arg == "-" || !arg.starts_with('-') {
+            parsed.files.push(PathBuf::from(arg));
+            continue;
+        }
+
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} needs a value"))
+        };
+        match name {
+            "--" => only_files = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-f" | "--follow" => parsed.follow = true,
+            "-l" | "--level" => {
+                let level = value()?;
+                parsed.filter.level = Some(
+                    level
+                        .parse()
+                        .map_err(|error| format!("{name} {level:?}: {error}"))?,
+                );
+            }
+            "-t" | "--target" => parsed.filter.targets.push(value()?),
+            "-F" | "--field" => {
+                let field = value()?;
+                let (key, value) = field
+                    .split_once('=')
+                    .ok_or_else(|| format!("{name} {field:?}: expected KEY=VALUE"))?;
+                parsed
+                    .filter
+                    .fields
+                    .push((key.to_string(), value.to_string()));
+            }
+            "--since" => parsed.filter.since = Some(time(name, &value()?)?),
+            "--until" => parsed.filter.until = Some(time(name, &value()?)?),
+            "--color" | "--colour" => {
+                parsed.colour_policy = match value()?.as_str() {
+                    "auto" => ColourPolicy::Auto,
+                    "always" => ColourPolicy::Always,
+                    "never" => ColourPolicy::Never,
+                    when => return Err(format!("{name} {when:?}: expected auto, always or never")),
+                }
+            }
+            name => return Err(format!("unknown option {name}")),
+        }
+    }
+    Ok(Command::View(parsed))
+}
+
+fn time(name: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|error| format!("{name} {value:?}: {error}"))
+}
+
+#[cfg(test)]
+mod args_tests {
+    use super::*;
+    use std_ex::log::LogLevel;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn should_parse_filters_and_files() {
+        let Ok(Command::View(args)) = parse_args(&[
+            "-l",
+            "warn",
+            "--target=app.db",
+            "-t",
+            "app.http",
+            "-F",
+            "user=alice",
+            "--since",
+            "2024-01-02T03:04:05Z",
+            "--follow",
+            "--color",
+            "never",
+            "a.log",
+            "-",
+            "--",
+            "-odd.log",
+        ]) else {
+            panic!("expected arguments to parse");
+        };
+        assert_eq!(args.filter.level, Some(LogLevel::Warn));
+        assert_eq!(args.filter.targets, vec!["app.db", "app.http"]);
+        assert_eq!(
+            args.filter.fields,
+            vec![(String::from("user"), String::from("alice"))]
+        );
+        assert!(args.filter.since.is_some() && args.filter.until.is_none());
+        assert!(args.follow);
+        assert_eq!(args.colour_policy, ColourPolicy::Never);
+        assert_eq!(
+            args.files,
+            vec![
+                PathBuf::from("a.log"),
+                PathBuf::from("-"),
+                PathBuf::from("-odd.log")
+            ]
+        );
+        assert_eq!(parse_args(&["a.log", "--help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn should_report_invalid_arguments() {
+        assert_eq!(
+            parse_args(&["--level"]),
+            Err(String::from("--level needs a value"))
+        );
+        assert!(
+            parse_args(&["--level", "loud"])
+                .unwrap_err()
+                .starts_with("--level \"loud\": ")
+        );
+        assert!(parse_args(&["-F", "user"]).is_err());
+        assert!(parse_args(&["--until", "yesterday"]).is_err());
+        assert!(parse_args(&["--color", "sometimes"]).is_err());
+        assert_eq!(
+            parse_args(&["--verbose"]),
+            Err(String::from("unknown option --verbose"))
+        );
+    }
+}
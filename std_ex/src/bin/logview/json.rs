@@ -0,0 +1,371 @@
+use std::{error::Error, fmt, fmt::Display};
+
+/// Deepest nesting parsed, so a hostile line can't overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value. Numbers are kept as written, so they are shown exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseJsonError {
+    reason: String,
+}
+
+impl ParseJsonError {
+    fn new<T: Into<String>>(reason: T) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ParseJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid json: {}", self.reason)
+    }
+}
+
+impl Error for ParseJsonError {}
+
+impl Json {
+    /// The value of the key, if this is an object containing it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The string, or the value written as JSON if it isn't one.
+    pub fn to_text(&self) -> String {
+        match self {
+            Json::String(s) => s.clone(),
+            json => json.to_string(),
+        }
+    }
+}
+
+/// Writes the value as compact JSON.
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => f.write_str(n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(pairs) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Parse a single JSON value, which may be surrounded by whitespace.
+pub fn parse(s: &str) -> Result<Json, ParseJsonError> {
+    let mut parser = Parser { s, pos: 0 };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    match parser.pos == s.len() {
+        true => Ok(value),
+        false => Err(parser.error("trailing characters")),
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Json, ParseJsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) if self.eat("true") => Ok(Json::Bool(true)),
+            Some(_) if self.eat("false") => Ok(Json::Bool(false)),
+            Some(_) if self.eat("null") => Ok(Json::Null),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, ParseJsonError> {
+        self.pos += 1;
+        let mut pairs = Vec::new();
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Json::Object(pairs));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(":") {
+                return Err(self.error("expected `:`"));
+            }
+            pairs.push((key, self.value(depth + 1)?));
+            self.skip_whitespace();
+            if self.eat("}") {
+                return Ok(Json::Object(pairs));
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected `,` or `}`"));
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, ParseJsonError> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(Json::Array(values));
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected `,` or `]`"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseJsonError> {
+        self.pos += 1;
+        let mut output = String::new();
+        loop {
+            let Some(c) = self.s[self.pos..].chars().next() else {
+                return Err(self.error("unclosed string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(output),
+                '\\' => output.push(self.escape()?),
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => output.push(c),
+            }
+        }
+    }
+
+    /// An escape sequence, after the backslash.
+    fn escape(&mut self) -> Result<char, ParseJsonError> {
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let high = self.hex4()?;
+                let code = match (0xD800..0xDC00).contains(&high) {
+                    true if self.eat("\\u") => {
+                        let low = self.hex4()?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err(self.error("invalid surrogate pair"));
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    true => return Err(self.error("unpaired surrogate")),
+                    false => high,
+                };
+                return char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"));
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseJsonError> {
+        let digits = self
+            .s
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))
+    }
+
+    fn number(&mut self) -> Result<Json, ParseJsonError> {
+        let start = self.pos;
+        self.eat("-");
+        let int = self.digits();
+        if int == 0 || (int > 1 && self.s.as_bytes()[self.pos - int] == b'0') {
+            return Err(self.error("invalid number"));
+        }
+        if self.eat(".") && self.digits() == 0 {
+            return Err(self.error("invalid number"));
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        Ok(Json::Number(self.s[start..self.pos].to_string()))
+    }
+
+    /// Skip ascii digits, returning how many there were.
+    fn digits(&mut self) -> usize {
+        let count = self.s.as_bytes()[self.pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        self.pos += count;
+        count
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.pos).copied()
+    }
+
+    /// Consume the literal if it is next.
+    fn eat(&mut self, literal: &str) -> bool {
+        match self.s[self.pos..].starts_with(literal) {
+            true => {
+                self.pos += literal.len();
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn error(&self, reason: &str) -> ParseJsonError {
+        ParseJsonError::new(format!("{reason} at {}", self.pos))
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_log_record() {
+        let json =
+            parse(r#" {"level":"WARN","n":-1.5e3,"ok":true,"none":null,"errors":["a","b"]} "#)
+                .unwrap();
+        assert_eq!(json.get("level").and_then(Json::as_str), Some("WARN"));
+        assert_eq!(json.get("n"), Some(&Json::Number(String::from("-1.5e3"))));
+        assert_eq!(json.get("ok"), Some(&Json::Bool(true)));
+        assert_eq!(json.get("none"), Some(&Json::Null));
+        assert_eq!(json.get("errors").unwrap().to_string(), r#"["a","b"]"#);
+        assert_eq!(json.get("missing"), None);
+    }
+
+    #[test]
+    fn should_decode_escapes() {
+        let json = parse(r#""quote \" slash \\ \/ line\n tab\t é 😀""#).unwrap();
+        assert_eq!(json.as_str(), Some("quote \" slash \\ / line\n tab\t é 😀"));
+        assert_eq!(
+            json.to_string(),
+            r#""quote \" slash \\ / line\n tab\t é 😀""#
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_json() {
+        for invalid in [
+            "",
+            "{",
+            r#"{"a"}"#,
+            r#"{"a":1,}"#,
+            "[1 2]",
+            "01",
+            "1.",
+            "-",
+            r#""\x""#,
+            r#""\ud83d""#,
+            "\"line\nbreak\"",
+            "nul",
+            "{} {}",
+        ] {
+            assert!(parse(invalid).is_err(), "{invalid:?}");
+        }
+        assert_eq!(
+            parse("[1,]").unwrap_err().to_string(),
+            "invalid json: expected a value at 3"
+        );
+    }
+
+    #[test]
+    fn should_limit_nesting() {
+        assert!(parse(&"[".repeat(MAX_DEPTH + 2)).is_err());
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(parse(&nested).is_ok());
+    }
+}
@@ -0,0 +1,229 @@
+//! Pretty print and filter JSON Lines logs, like those written by std_ex::log's Format::Json.
+//! Run `logview --help` for the options.
+
+mod args;
+mod json;
+mod view;
+
+use args::{Args, Command, USAGE};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+use std_ex::term::Stream;
+use view::Filter;
+
+/// How long to wait before checking followed files for more lines.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+fn main() -> ExitCode {
+    let args = match args::parse(env::args().skip(1)) {
+        Ok(Command::View(args)) => args,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("logview: {error}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("logview: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> io::Result<()> {
+    let mut viewer = Viewer {
+        filter: &args.filter,
+        colour: args.colour_policy.should_colour(Stream::Stdout),
+        output: io::stdout().lock(),
+    };
+    if args.files.is_empty() {
+        return viewer.read(io::stdin().lock());
+    }
+
+    let mut followed = Vec::new();
+    for path in &args.files {
+        if path.as_os_str() == "-" {
+            viewer.read(io::stdin().lock())?;
+            continue;
+        }
+        let file = File::open(path).map_err(|error| with_path(path, error))?;
+        match args.follow {
+            true => {
+                let mut file = Followed::new(path, file);
+                file.poll(&mut viewer)?;
+                followed.push(file);
+            }
+            false => viewer.read(BufReader::new(file))?,
+        }
+    }
+    if followed.is_empty() {
+        return Ok(());
+    }
+
+    viewer.output.flush()?;
+    loop {
+        let mut read_any = false;
+        for file in followed.iter_mut() {
+            read_any |= file.poll(&mut viewer)?;
+        }
+        if !read_any {
+            viewer.output.flush()?;
+            thread::sleep(FOLLOW_INTERVAL);
+        }
+    }
+}
+
+/// Shows the lines read which pass the filter.
+struct Viewer<'a, W: Write> {
+    filter: &'a Filter,
+    colour: bool,
+    output: W,
+}
+
+impl<W: Write> Viewer<'_, W> {
+    /// Show every line until the end of the input.
+    fn read<R: BufRead>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            self.show_bytes(&line)?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Show a line read as bytes, replacing any which aren't UTF-8.
+    fn show_bytes(&mut self, line: &[u8]) -> io::Result<()> {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        self.show(&String::from_utf8_lossy(line))
+    }
+
+    fn show(&mut self, line: &str) -> io::Result<()> {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        match json::parse(line) {
+            Ok(record @ json::Json::Object(_)) if self.filter.matches(&record) => {
+                writeln!(self.output, "{}", view::render(&record, self.colour))?;
+            }
+            Ok(json::Json::Object(_)) => {}
+            _ if self.filter.is_empty() => writeln!(self.output, "{line}")?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// A file being followed, remembering any partly written last line, which may end part way
+/// through a character.
+struct Followed<'a> {
+    path: &'a Path,
+    reader: BufReader<File>,
+    position: u64,
+    partial: Vec<u8>,
+}
+
+impl<'a> Followed<'a> {
+    fn new(path: &'a Path, file: File) -> Self {
+        Self {
+            path,
+            reader: BufReader::new(file),
+            position: 0,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Show any lines written since last time, starting again if the file was truncated.
+    /// Returns whether anything was read.
+    fn poll<W: Write>(&mut self, viewer: &mut Viewer<W>) -> io::Result<bool> {
+        let len = self
+            .reader
+            .get_ref()
+            .metadata()
+            .map_err(|error| with_path(self.path, error))?
+            .len();
+        if len < self.position {
+            self.reader.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+            self.partial.clear();
+        }
+
+        let mut read_any = false;
+        loop {
+            let read = self.reader.read_until(b'\n', &mut self.partial)?;
+            if read == 0 {
+                return Ok(read_any);
+            }
+            read_any = true;
+            self.position += read as u64;
+            if self.partial.ends_with(b"\n") {
+                let line = std::mem::take(&mut self.partial);
+                viewer.show_bytes(&line)?;
+            }
+        }
+    }
+}
+
+fn with_path(path: &Path, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod main_tests {
+    use super::*;
+    use std::{fs, process};
+
+    fn view(input: &[u8]) -> String {
+        let filter = Filter::default();
+        let mut viewer = Viewer {
+            filter: &filter,
+            colour: false,
+            output: Vec::new(),
+        };
+        viewer.read(input).unwrap();
+        String::from_utf8(viewer.output).unwrap()
+    }
+
+    #[test]
+    fn should_show_lines_which_are_not_utf8() {
+        assert_eq!(
+            view(b"caf\xe9\r\n{\"level\":\"INFO\",\"message\":\"hi\"}\nlast"),
+            "caf\u{fffd}\nINFO: hi\nlast\n"
+        );
+    }
+
+    #[test]
+    fn should_keep_a_character_split_across_polls() {
+        let path = env::temp_dir().join(format!("logview_follow_{}.log", process::id()));
+        fs::write(&path, b"caf\xc3").unwrap();
+        let filter = Filter::default();
+        let mut viewer = Viewer {
+            filter: &filter,
+            colour: false,
+            output: Vec::new(),
+        };
+        let mut followed = Followed::new(&path, File::open(&path).unwrap());
+        assert!(followed.poll(&mut viewer).unwrap());
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"\xa9\n")
+            .unwrap();
+        assert!(followed.poll(&mut viewer).unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(String::from_utf8(viewer.output).unwrap(), "café\n");
+    }
+}
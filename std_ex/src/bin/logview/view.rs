@@ -0,0 +1,286 @@
+use crate::json::Json;
+use std_ex::chrono::{DateTime, Local, Utc};
+use std_ex::log::LogLevel;
+use std_ex::term::Colour;
+
+/// Keys written by Format::Json which are laid out, rather than shown as `key=value` fields.
+const KNOWN_KEYS: [&str; 9] = [
+    "timestamp",
+    "level",
+    "message",
+    "target",
+    "prefix",
+    "data",
+    "errors",
+    "backtrace",
+    "context",
+];
+
+/// Which records are shown. Records missing a part being filtered on are not shown.
+#[derive(Debug, Default, PartialEq)]
+pub struct Filter {
+    /// The lowest level shown.
+    pub level: Option<LogLevel>,
+    /// Targets shown, along with those nested beneath them, e.g. `app.db` shows `app.db.pool`.
+    pub targets: Vec<String>,
+    /// Fields which must have the value.
+    pub fields: Vec<(String, String)>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Filter {
+    /// Does the filter show everything.
+    pub fn is_empty(&self) -> bool {
+        *self == Filter::default()
+    }
+
+    /// Should the record be shown.
+    pub fn matches(&self, record: &Json) -> bool {
+        if let Some(min) = self.level
+            && level(record).is_none_or(|level| level < min)
+        {
+            return false;
+        }
+        if !self.targets.is_empty() {
+            let target = text(record, "target").unwrap_or_default();
+            if !self.targets.iter().any(|t| is_descendant(&target, t)) {
+                return false;
+            }
+        }
+        for (key, value) in &self.fields {
+            if record.get(key).map(Json::to_text).as_ref() != Some(value) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Some(time) = time(record) else {
+                return false;
+            };
+            if self.since.is_some_and(|since| time < since)
+                || self.until.is_some_and(|until| time > until)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Lay out a record like the default text template,
+/// `[time] LEVEL: target: prefix message key=value`, followed by any data, error chain,
+/// backtrace and context on the next lines. Control characters other than newlines and tabs in
+/// the record are escaped, e.g. `\u{1b}`, so it can't send the terminal its own sequences; only
+/// the level is coloured.
+pub fn render(record: &Json, colour: bool) -> String {
+    let mut output = String::new();
+    match time(record) {
+        Some(time) => {
+            let time = time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S%.3f");
+            output.push_str(&format!("[{time}] "));
+        }
+        None => {
+            if let Some(time) = shown(record, "timestamp") {
+                output.push_str(&format!("[{time}] "));
+            }
+        }
+    }
+
+    let level_text = shown(record, "level").unwrap_or_else(|| String::from("-"));
+    match level(record) {
+        Some(level) if colour => {
+            let name = level.to_string().to_uppercase();
+            output.push_str(&Colour::from(level).paint(name));
+        }
+        Some(level) => output.push_str(&level.to_string().to_uppercase()),
+        None => output.push_str(&level_text),
+    }
+    output.push(':');
+
+    for key in ["target", "prefix", "message"] {
+        if let Some(value) = shown(record, key).filter(|value| !value.is_empty()) {
+            output.push(' ');
+            output.push_str(&value);
+            if key == "target" {
+                output.push(':');
+            }
+        }
+    }
+
+    if let Json::Object(pairs) = record {
+        for (key, value) in pairs {
+            if KNOWN_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            let key = escape_controls(key);
+            let value = escape_controls(&value.to_text());
+            let needs_quotes = value.is_empty()
+                || value
+                    .chars()
+                    .any(|c| c.is_whitespace() || c == '=' || c == '"');
+            match needs_quotes {
+                true => output.push_str(&format!(" {key}={value:?}")),
+                false => output.push_str(&format!(" {key}={value}")),
+            }
+        }
+    }
+
+    if let Some(data) = shown(record, "data") {
+        output.push('\n');
+        output.push_str(&data);
+    }
+    if let Some(Json::Array(errors)) = record.get("errors") {
+        for (i, error) in errors.iter().enumerate() {
+            match i {
+                0 => output.push_str("\nerror: "),
+                _ => output.push_str("\n  caused by: "),
+            }
+            output.push_str(&escape_controls(&error.to_text()));
+        }
+    }
+    if let Some(backtrace) = shown(record, "backtrace") {
+        output.push_str("\nBacktrace:\n");
+        output.push_str(&backtrace);
+    }
+    if let Some(context) = shown(record, "context") {
+        output.push_str("\nContext:\n");
+        output.push_str(&context);
+    }
+    output
+}
+
+fn text(record: &Json, key: &str) -> Option<String> {
+    record.get(key).map(Json::to_text)
+}
+
+/// The text of a key, with its control characters escaped for the terminal.
+fn shown(record: &Json, key: &str) -> Option<String> {
+    text(record, key).map(|text| escape_controls(&text))
+}
+
+/// Escape control characters other than newlines and tabs, including the C1 controls.
+fn escape_controls(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c.is_control() && c != '\n' && c != '\t' {
+            true => escaped.extend(c.escape_default()),
+            false => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn level(record: &Json) -> Option<LogLevel> {
+    record.get("level")?.as_str()?.parse().ok()
+}
+
+fn time(record: &Json) -> Option<DateTime<Utc>> {
+    let timestamp = record.get("timestamp")?.as_str()?;
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Is the target the filter's target, or nested beneath it.
+fn is_descendant(target: &str, filter: &str) -> bool {
+    match target.strip_prefix(filter) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod view_tests {
+    use super::*;
+    use crate::json;
+
+    const RECORD: &str = r#"{"timestamp":"2024-01-02T03:04:05.000Z","level":"WARN","message":"slow query","target":"app.db","prefix":"pool:","data":"[\n    1,\n]","errors":["timed out","reset"],"context":"1","user":"alice smith","attempt":2}"#;
+
+    fn record() -> Json {
+        json::parse(RECORD).unwrap()
+    }
+
+    #[test]
+    fn should_lay_out_record() {
+        let time = DateTime::parse_from_rfc3339("2024-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f");
+        assert_eq!(
+            render(&record(), false),
+            format!(
+                "[{time}] WARN: app.db: pool: slow query user=\"alice smith\" attempt=2\n[\n    1,\n]\nerror: timed out\n  caused by: reset\nContext:\n1"
+            )
+        );
+        let minimal = json::parse(r#"{"level":"oops","message":"hi"}"#).unwrap();
+        assert_eq!(render(&minimal, true), "oops: hi");
+    }
+
+    #[test]
+    fn should_escape_control_characters() {
+        let record = json::parse(
+            r#"{"level":"\u001b[2J","message":"\u001b]0;x\u0007","target":"a\u009bb","key\u001b":"\u001b[31m","data":"[\n\u0007]","errors":["\u001b"]}"#,
+        )
+        .unwrap();
+        let rendered = render(&record, true);
+        assert_eq!(
+            rendered,
+            r"\u{1b}[2J: a\u{9b}b: \u{1b}]0;x\u{7} key\u{1b}=\u{1b}[31m".to_string()
+                + "\n[\n"
+                + r"\u{7}]"
+                + "\nerror: "
+                + r"\u{1b}"
+        );
+        assert!(!rendered.chars().any(|c| c.is_control() && c != '\n'));
+    }
+
+    #[test]
+    fn should_colour_level() {
+        let rendered = render(&record(), true);
+        assert!(rendered.contains(&Colour::from(LogLevel::Warn).paint("WARN")));
+    }
+
+    #[test]
+    fn should_filter_by_level_target_and_field() {
+        let record = record();
+        assert!(Filter::default().matches(&record));
+
+        let level = |level| Filter {
+            level: Some(level),
+            ..Filter::default()
+        };
+        assert!(level(LogLevel::Warn).matches(&record));
+        assert!(!level(LogLevel::Error).matches(&record));
+
+        let target = |target: &str| Filter {
+            targets: vec![String::from(target)],
+            ..Filter::default()
+        };
+        assert!(target("app").matches(&record));
+        assert!(target("app.db").matches(&record));
+        assert!(!target("app.d").matches(&record));
+
+        let field = |key: &str, value: &str| Filter {
+            fields: vec![(String::from(key), String::from(value))],
+            ..Filter::default()
+        };
+        assert!(field("attempt", "2").matches(&record));
+        assert!(!field("user", "alice").matches(&record));
+        assert!(!field("missing", "").matches(&record));
+    }
+
+    #[test]
+    fn should_filter_by_time_range() {
+        let time = |s| Some(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc));
+        let range = |since, until| Filter {
+            since: time(since),
+            until: time(until),
+            ..Filter::default()
+        };
+        assert!(range("2024-01-02T03:00:00Z", "2024-01-02T04:00:00Z").matches(&record()));
+        assert!(!range("2024-01-02T03:05:00Z", "2024-01-02T04:00:00Z").matches(&record()));
+        assert!(!range("2024-01-02T02:00:00Z", "2024-01-02T03:00:00Z").matches(&record()));
+        let untimed = json::parse(r#"{"message":"hi"}"#).unwrap();
+        assert!(!range("2024-01-02T02:00:00Z", "2024-01-02T04:00:00Z").matches(&untimed));
+    }
+}
@@ -23,8 +23,26 @@ pub fn var_exists<K>(key: K) -> bool
 where
     K: AsRef<OsStr>,
 {
-    match var(key) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+    var(key).is_ok()
+}
+
+/// Best effort lookup of the host name of the machine, without any external dependencies.
+///
+/// Reads /proc/sys/kernel/hostname, then /etc/hostname, then the HOSTNAME environment variable.
+/// Returns None if none of them contain a host name.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::env;
+///
+/// let hostname = env::hostname().unwrap_or(String::from("localhost"));
+/// ```
+pub fn hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .chain(var("HOSTNAME").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .find(|hostname| !hostname.is_empty())
 }
@@ -8,7 +8,7 @@ pub use std::fmt::*;
 /// use std_ex::fmt;
 ///
 /// let data = Vec::from([1,2,3]);
-/// let pretty_string = fmt::pretty(data);
+/// let pretty_string = fmt::pretty(&data);
 ///
 /// ```
 pub fn pretty(data: &dyn Debug) -> String {
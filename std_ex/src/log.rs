@@ -1,10 +1,61 @@
-use crate::sync::{LazyLock, Mutex, Once};
+use crate::sync::{LazyLock, Mutex, Once, OnceLock};
+use crate::term::ColourPolicy;
 
+pub use audit::*;
+pub use command::*;
+pub use directives::*;
+pub use env_config::EnvConfigError;
+pub use file::*;
+pub use format::{Format, Multiline};
+#[cfg(unix)]
+pub use journald::*;
+pub use lambda::{clear_request_id, set_request_id};
+pub use limits::Limits;
 pub use log::*;
 pub use log_level::*;
+pub use metrics::{LevelCounts, LogMetrics, count_dropped, metrics, reset_metrics, target_metrics};
+pub use network::*;
+pub use rate_limit::{RateLimit, RateLimitKey};
+pub use record::*;
+pub use redact::*;
+pub use result_ext::*;
+pub use ring_buffer::*;
+pub use sampling::Sampling;
+pub use sink::*;
+pub use static_level::STATIC_MAX_LEVEL;
+pub use syslog::*;
+pub use template::*;
+pub use writer::*;
 
+mod audit;
+mod command;
+mod directives;
+mod duplicates;
+mod env_config;
+mod error_chain;
+mod file;
+mod format;
+#[cfg(unix)]
+mod journald;
+mod lambda;
+mod limits;
+#[allow(clippy::module_inception)]
 mod log;
 mod log_level;
+mod metrics;
+mod network;
+mod rate_limit;
+mod record;
+mod redact;
+mod result_ext;
+mod ring_buffer;
+mod sampling;
+mod sha256;
+mod sink;
+mod static_level;
+mod syslog;
+mod template;
+mod writer;
 
 static LOG_BUILDER: LazyLock<Mutex<LogrsBuilder>> = LazyLock::new(|| Mutex::new(Log::new_ex()));
 static LOG: LazyLock<Log> = LazyLock::new(|| match LOG_BUILDER.lock() {
@@ -12,16 +63,131 @@ static LOG: LazyLock<Log> = LazyLock::new(|| match LOG_BUILDER.lock() {
     Err(_) => Log::default(),
 });
 
+static INIT_FROM_ENV: OnceLock<Result<(), EnvConfigError>> = OnceLock::new();
+/// Configure the global logrs instance from the LOG_LEVEL, LOG_FORMAT, LOG_TIMESTAMP, LOG_COLOR
+/// and LOG_FILE environment variables, see LogrsBuilder::configure_from_env.
+/// Returns an error listing every invalid variable, in which case nothing is changed, and loggers
+/// don't also warn about an invalid LOG_LEVEL.
+/// This function will only modify the global instance once. Further calls do nothing, and return
+/// the result of the first.
+/// Once the first logging function has been called, the instance can no longer be modified.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log;
+///
+/// // e.g. LOG_LEVEL=debug LOG_FORMAT=json LOG_FILE=app.log
+/// if let Err(error) = log::init_from_env() {
+///     eprintln!("{error}");
+/// }
+/// log::info("configured");
+/// ```
+pub fn init_from_env() -> Result<(), EnvConfigError> {
+    INIT_FROM_ENV
+        .get_or_init(|| match LOG_BUILDER.lock() {
+            Ok(mut builder) => builder.configure_from_env().map(|_| ()).inspect_err(|_| {
+                log::mark_env_error_reported();
+            }),
+            Err(_) => Ok(()),
+        })
+        .clone()
+}
+
 static ONCE_SET_LOG_LEVEL: Once = Once::new();
 /// Set the default log level on the global logrs instance.
 /// This function will only modify the global instance once. Further calls do nothing.
 /// Once the first logging function has been called, the instance can no longer be modified.
 pub fn set_log_level(log_level: LogLevel) {
-    ONCE_SET_LOG_LEVEL.call_once(|| match LOG_BUILDER.lock() {
-        Ok(mut builder) => {
+    ONCE_SET_LOG_LEVEL.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
             builder.set_log_level(log_level);
         }
-        Err(_) => return,
+    })
+}
+
+static ONCE_SET_NAME: Once = Once::new();
+/// Set the name of the global logrs instance, which leads the target of every named logger.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_name(name: &str) {
+    ONCE_SET_NAME.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_name(name);
+        }
+    })
+}
+
+static ONCE_ADD_PROCESS_FIELDS: Once = Once::new();
+/// Add `service`, `host`, `pid` and `version` fields to the global logrs instance, see
+/// LogrsBuilder::add_process_fields.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn add_process_fields(service: &str, version: &str) {
+    ONCE_ADD_PROCESS_FIELDS.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.add_process_fields(service, version);
+        }
+    })
+}
+
+static ONCE_SET_LEVEL_DIRECTIVES: Once = Once::new();
+/// Set the level directives on the global logrs instance, e.g. `warn,app.db=debug`.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_level_directives(level_directives: LevelDirectives) {
+    ONCE_SET_LEVEL_DIRECTIVES.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_level_directives(level_directives);
+        }
+    })
+}
+
+static ONCE_SET_REDACTION: Once = Once::new();
+/// Set which secrets are redacted by the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_redaction(redaction: Redaction) {
+    ONCE_SET_REDACTION.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_redaction(redaction);
+        }
+    })
+}
+
+static ONCE_SET_LIMITS: Once = Once::new();
+/// Set size limits for each record of the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_limits(limits: Limits) {
+    ONCE_SET_LIMITS.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_limits(limits);
+        }
+    })
+}
+
+static ONCE_SET_RATE_LIMIT: Once = Once::new();
+/// Limit how often records are logged by the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_rate_limit(rate_limit: RateLimit) {
+    ONCE_SET_RATE_LIMIT.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_rate_limit(rate_limit);
+        }
+    })
+}
+
+static ONCE_COLLAPSE_DUPLICATES: Once = Once::new();
+/// Collapse consecutive identical records logged by the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn collapse_duplicates() {
+    ONCE_COLLAPSE_DUPLICATES.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.collapse_duplicates();
+        }
     })
 }
 
@@ -30,11 +196,46 @@ static ONCE_SET_TIMESTAMP: Once = Once::new();
 /// This function will only modify the global instance once. Further calls do nothing.
 /// Once the first logging function has been called, the instance can no longer be modified.
 pub fn set_timestamp(timestamp: Timestamp) {
-    ONCE_SET_TIMESTAMP.call_once(|| match LOG_BUILDER.lock() {
-        Ok(mut builder) => {
+    ONCE_SET_TIMESTAMP.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
             builder.set_timestamp(timestamp);
         }
-        Err(_) => return,
+    })
+}
+
+static ONCE_SET_FORMAT: Once = Once::new();
+/// Set the output format on the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_format(format: Format) {
+    ONCE_SET_FORMAT.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_format(format);
+        }
+    })
+}
+
+static ONCE_SET_MULTILINE: Once = Once::new();
+/// Set how line breaks in text logs are written on the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_multiline(multiline: Multiline) {
+    ONCE_SET_MULTILINE.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_multiline(multiline);
+        }
+    })
+}
+
+static ONCE_SET_TEMPLATE: Once = Once::new();
+/// Set the template used to lay out messages on the global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_template(template: Template) {
+    ONCE_SET_TEMPLATE.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_template(template);
+        }
     })
 }
 
@@ -43,11 +244,22 @@ static ONCE_SET_LOGGING_FUNCTION: Once = Once::new();
 /// This function will only modify the global instance once. Further calls do nothing.
 /// Once the first logging function has been called, the instance can no longer be modified.
 pub fn set_logging_function(f: fn(String) -> ()) {
-    ONCE_SET_LOGGING_FUNCTION.call_once(|| match LOG_BUILDER.lock() {
-        Ok(mut builder) => {
+    ONCE_SET_LOGGING_FUNCTION.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
             builder.set_logging_function(f);
         }
-        Err(_) => return,
+    })
+}
+
+static ONCE_SET_SINK: Once = Once::new();
+/// Send every log from the global logrs instance to the sink, instead of the logging function.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_sink<S: Sink + 'static>(sink: S) {
+    ONCE_SET_SINK.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_sink(sink);
+        }
     })
 }
 
@@ -56,40 +268,151 @@ static ONCE_DISABLE_ANSI: Once = Once::new();
 /// This function will only modify the global instance once. Further calls do nothing.
 /// Once the first logging function has been called, the instance can no longer be modified.
 pub fn disable_ansi() {
-    ONCE_DISABLE_ANSI.call_once(|| match LOG_BUILDER.lock() {
-        Ok(mut builder) => {
+    ONCE_DISABLE_ANSI.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
             builder.disable_ansi();
         }
-        Err(_) => return,
     })
 }
 
+static ONCE_SET_COLOUR_POLICY: Once = Once::new();
+/// Set when internal ansi codes are used on global logrs instance.
+/// This function will only modify the global instance once. Further calls do nothing.
+/// Once the first logging function has been called, the instance can no longer be modified.
+pub fn set_colour_policy(colour_policy: ColourPolicy) {
+    ONCE_SET_COLOUR_POLICY.call_once(|| {
+        if let Ok(mut builder) = LOG_BUILDER.lock() {
+            builder.set_colour_policy(colour_policy);
+        }
+    })
+}
+
+/// A named logger inheriting from the global logrs instance, see Log::named.
+/// Once called, the global instance can no longer be modified.
+pub fn named(name: &str) -> Log {
+    LOG.named(name)
+}
+
 macro_rules! log_fns_at_level {
-    ($log_name: ident, $logf_name:ident, $logp_name:ident) => {
+    ($log_name: ident, $logf_name:ident, $logp_name:ident, $log_once_name:ident, $level: ident) => {
         /// Log a str using a global log instance.
-        pub fn $log_name<T: AsRef<str>>(msg: T)
+        #[track_caller]
+        pub fn $log_name<T>(msg: T)
         where
-            T: crate::fmt::Display,
+            T: AsRef<str> + crate::fmt::Display,
         {
-            LOG.$log_name(msg);
+            if const { static_level::enabled(LogLevel::$level) } {
+                LOG.$log_name(msg);
+            }
         }
         /// Log a str with data using a global log instance.
-        pub fn $logf_name<T: AsRef<str>>(msg: T, data: &dyn crate::fmt::Debug)
+        #[track_caller]
+        pub fn $logf_name<T>(msg: T, data: &dyn crate::fmt::Debug)
         where
-            T: crate::fmt::Display,
+            T: AsRef<str> + crate::fmt::Display,
         {
-            LOG.$logf_name(msg, data);
+            if const { static_level::enabled(LogLevel::$level) } {
+                LOG.$logf_name(msg, data);
+            }
         }
         /// Log data using crate_ex::fmt::pretty using a global log instance.
+        #[track_caller]
         pub fn $logp_name(data: &dyn crate::fmt::Debug) {
-            LOG.$logp_name(data);
+            if const { static_level::enabled(LogLevel::$level) } {
+                LOG.$logp_name(data);
+            }
+        }
+        /// Log a str the first time this call site is reached only, using a global log instance.
+        #[track_caller]
+        pub fn $log_once_name<T>(msg: T)
+        where
+            T: AsRef<str> + crate::fmt::Display,
+        {
+            if const { static_level::enabled(LogLevel::$level) } {
+                LOG.$log_once_name(msg);
+            }
         }
     };
 }
 
-log_fns_at_level!(trace, tracef, tracep);
-log_fns_at_level!(debug, debugf, debugp);
-log_fns_at_level!(info, infof, infop);
-log_fns_at_level!(warn, warnf, warnp);
-log_fns_at_level!(error, errorf, errorp);
-log_fns_at_level!(fatal, fatalf, fatalp);
+/// Log a message with an error and every error in its source chain, at error level, using a
+/// global log instance.
+#[track_caller]
+pub fn error_chain<T>(msg: T, error: &dyn crate::error::Error)
+where
+    T: AsRef<str> + crate::fmt::Display,
+{
+    if const { static_level::enabled(LogLevel::Error) } {
+        LOG.error_chain(msg, error);
+    }
+}
+
+/// Log an expression and its pretty printed value at debug level using a global log instance,
+/// used by log_dbg!.
+#[track_caller]
+pub fn debug_expr(expr: &str, value: &dyn crate::fmt::Debug) {
+    if const { static_level::enabled(LogLevel::Debug) } {
+        LOG.debug_expr(expr, value);
+    }
+}
+
+/// Log expressions and their values at debug level, like std::dbg! but through the logger.
+///
+/// Each expression is logged with its source location and text as the message, and its pretty
+/// printed value as data, e.g. `DEBUG: [src/main.rs:4:13] a * 2` followed by `4`. The value is
+/// returned by move, and several expressions are returned as a tuple. With no expressions just
+/// the location is logged.
+///
+/// `log_dbg!(expr)` logs using the global log instance, and `log_dbg!(log; expr)` through the
+/// given logger.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::Log;
+/// use std_ex::log_dbg;
+///
+/// let log = Log::new();
+/// let a = log_dbg!(1 + 1);
+/// let (b, c) = log_dbg!(&log; a * 2, String::from("moved"));
+/// assert_eq!((a, b, c.as_str()), (2, 4, "moved"));
+/// ```
+#[macro_export]
+macro_rules! log_dbg {
+    () => {
+        $crate::log::debug(format!("[{}:{}:{}]", file!(), line!(), column!()))
+    };
+    ($log:expr;) => {
+        $log.debug(format!("[{}:{}:{}]", file!(), line!(), column!()))
+    };
+    ($log:expr; $val:expr $(,)?) => {
+        match $val {
+            tmp => {
+                $log.debug_expr(stringify!($val), &tmp);
+                tmp
+            }
+        }
+    };
+    ($log:expr; $($val:expr),+ $(,)?) => {{
+        let log: &$crate::log::Log = &$log;
+        ($($crate::log_dbg!(log; $val)),+,)
+    }};
+    ($val:expr $(,)?) => {
+        match $val {
+            tmp => {
+                $crate::log::debug_expr(stringify!($val), &tmp);
+                tmp
+            }
+        }
+    };
+    ($($val:expr),+ $(,)?) => {
+        ($($crate::log_dbg!($val)),+,)
+    };
+}
+
+log_fns_at_level!(trace, tracef, tracep, trace_once, Trace);
+log_fns_at_level!(debug, debugf, debugp, debug_once, Debug);
+log_fns_at_level!(info, infof, infop, info_once, Info);
+log_fns_at_level!(warn, warnf, warnp, warn_once, Warn);
+log_fns_at_level!(error, errorf, errorp, error_once, Error);
+log_fns_at_level!(fatal, fatalf, fatalp, fatal_once, Fatal);
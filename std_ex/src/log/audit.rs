@@ -0,0 +1,472 @@
+use crate::fs::{File, OpenOptions};
+use crate::io::{self, BufRead, BufReader, Write};
+use crate::log::{LogLevel, Record, Sink, count_dropped, format, sha256};
+use crate::path::Path;
+use crate::sync::Mutex;
+use crate::{error::Error, fmt, fmt::Display};
+
+/// The prev of the first record in a chain.
+pub const AUDIT_GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Keys the chain adds to each record, which fields can't use.
+const LINK_KEYS: [&str; 3] = ["seq", "prev", "hash"];
+
+/// A sink writing a tamper evident audit trail, one JSON record per line.
+///
+/// Each record is the same JSON as Format::Json, led by a `seq` number counting up from 1 and
+/// the `prev` hash of the record before it, and ending with its own `hash`, the SHA-256 of the
+/// record up to that key. Fields with those names are written as `fields.seq` and so on, as
+/// Format::Json does for its own keys. Editing, reordering or removing records breaks the chain, which
+/// verify_audit_file reports, except removing records from the end, which leaves a shorter chain
+/// that still verifies. That, and rewriting the whole file with a recomputed chain, can only be
+/// detected against the latest seq and hash, so ship them somewhere else for stronger guarantees.
+///
+/// The sink takes records at every level, whatever the logger's level, LOG_LEVEL or
+/// AWS_LAMBDA_LOG_LEVEL, though not those below STATIC_MAX_LEVEL. Give the audit trail its own
+/// logger, so it isn't sampled or rate limited like the application's logs.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std_ex::log::{self, AuditSink, Log};
+///
+/// let audit = Log::new_ex()
+///     .set_sink(AuditSink::open("audit.log").unwrap())
+///     .done();
+/// audit.child_with_field("user", "alice").info("deleted invoice 42");
+///
+/// let records = log::verify_audit_file("audit.log").unwrap();
+/// ```
+pub struct AuditSink {
+    chain: Mutex<Chain>,
+}
+
+struct Chain {
+    output: Box<dyn Write + Send>,
+    seq: u64,
+    prev: String,
+    /// The file written to by open and the length of its whole records, to cut a record which
+    /// failed part way back off.
+    file: Option<(File, u64)>,
+}
+
+/// Why an audit trail failed verification, and the line of the first broken link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditError {
+    line: u64,
+    reason: String,
+}
+
+impl AuditSink {
+    /// Append to the file, creating it if needed, continuing the chain from its last record.
+    /// A last line without a newline is kept, and finished, if it is an audit record, or else
+    /// removed first as left by a write which failed part way.
+    /// Errors if the last line of the file isn't an audit record.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let (mut last, mut len, unfinished) = last_line(&mut file)?;
+        if !unfinished.is_empty() {
+            match String::from_utf8(unfinished) {
+                Ok(line) if Link::parse(&line).is_some() => {
+                    file.write_all(b"\n")?;
+                    len += line.len() as u64 + 1;
+                    last = Some(line);
+                }
+                _ => file.set_len(len)?,
+            }
+        }
+        let (seq, prev) = match last {
+            None => (0, String::from(AUDIT_GENESIS)),
+            Some(line) => match Link::parse(&line) {
+                Some(link) => (link.seq, link.hash.to_string()),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "last line of audit log is not an audit record",
+                    ));
+                }
+            },
+        };
+        let mut sink = Self::with(file.try_clone()?, seq, prev);
+        sink.chain.get_mut().unwrap().file = Some((file, len));
+        Ok(sink)
+    }
+
+    /// Write to the output, starting a new chain.
+    pub fn new<W: Write + Send + 'static>(output: W) -> Self {
+        Self::with(output, 0, String::from(AUDIT_GENESIS))
+    }
+
+    fn with<W: Write + Send + 'static>(output: W, seq: u64, prev: String) -> Self {
+        Self {
+            chain: Mutex::new(Chain {
+                output: Box::new(output),
+                seq,
+                prev,
+                file: None,
+            }),
+        }
+    }
+}
+
+impl Sink for AuditSink {
+    fn log(&self, record: &Record, _formatted: &str) {
+        let mut chain = self
+            .chain
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (mut line, hash) = link(
+            chain.seq + 1,
+            &chain.prev,
+            &format::json_reserving(record, &LINK_KEYS),
+        );
+        line.push('\n');
+        let written = chain
+            .output
+            .write_all(line.as_bytes())
+            .and_then(|_| chain.output.flush());
+        match written {
+            Ok(()) => {
+                chain.seq += 1;
+                chain.prev = hash;
+                if let Some((_, len)) = chain.file.as_mut() {
+                    *len += line.len() as u64;
+                }
+            }
+            Err(_) => {
+                // so the next record starts on its own line, if any of this one was written
+                if let Some((file, len)) = &chain.file {
+                    let _ = file.set_len(*len);
+                }
+                count_dropped(record.level, &record.target)
+            }
+        }
+    }
+
+    /// Every level, so audit records aren't filtered by the logger's levels.
+    fn level(&self) -> Option<LogLevel> {
+        Some(LogLevel::Trace)
+    }
+}
+
+impl AuditError {
+    fn new<T: Into<String>>(line: u64, reason: T) -> Self {
+        Self {
+            line,
+            reason: reason.into(),
+        }
+    }
+
+    /// The line of the first broken link, counting from 1, or 0 if the file couldn't be opened.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "invalid audit log: {}", self.reason),
+            line => write!(f, "invalid audit log at line {line}: {}", self.reason),
+        }
+    }
+}
+
+impl Error for AuditError {}
+
+/// Walk an audit trail written by AuditSink, checking every link of the chain.
+/// Returns the number of records, or the first broken link.
+pub fn verify_audit<R: BufRead>(reader: R) -> Result<u64, AuditError> {
+    let mut prev = String::from(AUDIT_GENESIS);
+    let mut seq = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let number = i as u64 + 1;
+        let line = line.map_err(|error| AuditError::new(number, format!("unreadable, {error}")))?;
+        let Some(link) = Link::parse(&line) else {
+            return Err(AuditError::new(number, "not an audit record"));
+        };
+        if link.seq != seq + 1 {
+            return Err(AuditError::new(
+                number,
+                format!("expected seq {} but found {}", seq + 1, link.seq),
+            ));
+        }
+        if link.prev != prev {
+            return Err(AuditError::new(
+                number,
+                "prev does not match the hash of the record before it",
+            ));
+        }
+        if sha256::hex_digest(link.body.as_bytes()) != link.hash {
+            return Err(AuditError::new(number, "hash does not match the record"));
+        }
+        seq = link.seq;
+        prev = link.hash.to_string();
+    }
+    Ok(seq)
+}
+
+/// Walk an audit trail file written by AuditSink, see verify_audit.
+pub fn verify_audit_file<P: AsRef<Path>>(path: P) -> Result<u64, AuditError> {
+    let file = File::open(path)
+        .map_err(|error| AuditError::new(0, format!("could not be opened, {error}")))?;
+    verify_audit(BufReader::new(file))
+}
+
+/// Chain a JSON object after the record with the prev hash.
+/// Returns the line to write and its hash.
+fn link(seq: u64, prev: &str, json: &str) -> (String, String) {
+    let fields = json.strip_prefix('{').unwrap_or(json);
+    let body = match fields {
+        "}" => format!("{{\"seq\":{seq},\"prev\":\"{prev}\"}}"),
+        fields => format!("{{\"seq\":{seq},\"prev\":\"{prev}\",{fields}"),
+    };
+    let hash = sha256::hex_digest(body.as_bytes());
+    let line = format!("{},\"hash\":\"{hash}\"}}", &body[..body.len() - 1]);
+    (line, hash)
+}
+
+/// A line written by AuditSink, split into its parts.
+struct Link<'a> {
+    seq: u64,
+    prev: &'a str,
+    hash: &'a str,
+    /// The record without its hash, which is what was hashed.
+    body: String,
+}
+
+impl<'a> Link<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let (rest, hash) = line.strip_suffix("\"}")?.rsplit_once(",\"hash\":\"")?;
+        let (seq, rest) = rest.strip_prefix("{\"seq\":")?.split_once(',')?;
+        let (prev, _) = rest.strip_prefix("\"prev\":\"")?.split_once('"')?;
+        Some(Self {
+            seq: seq.parse().ok()?,
+            prev,
+            hash,
+            body: format!("{}}}", &line[..line.len() - hash.len() - 11]),
+        })
+    }
+}
+
+/// The last non empty line of a file ending in a newline, None if there is none, the length of
+/// the file up to the end of that line, and anything after it.
+fn last_line(file: &mut File) -> io::Result<(Option<String>, u64, Vec<u8>)> {
+    let mut reader = BufReader::new(file);
+    let mut last = None;
+    let mut len = 0;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if line.last() != Some(&b'\n') {
+            return Ok((last, len, line));
+        }
+        len += read as u64;
+        line.pop();
+        if !line.is_empty() {
+            let line = String::from_utf8(line.clone())
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            last = Some(line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use super::*;
+    use crate::log::{Log, LogLevel, static_level};
+    use crate::sync::Arc;
+    use crate::{env, fs, process};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Three chained records.
+    fn trail() -> Vec<String> {
+        let output = Output::default();
+        let log = Log::new_ex()
+            .set_sink(AuditSink::new(output.clone()))
+            .done();
+        log.error("first");
+        log.child_with_field("user", "alice").error("second");
+        log.errorf("third", &"data");
+        let written = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        written.lines().map(String::from).collect()
+    }
+
+    fn verify(lines: &[String]) -> Result<u64, AuditError> {
+        verify_audit(lines.join("\n").as_bytes())
+    }
+
+    #[test]
+    fn should_chain_records() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let lines = trail();
+        assert!(lines[0].starts_with(&format!("{{\"seq\":1,\"prev\":\"{AUDIT_GENESIS}\",")));
+        assert!(lines[1].contains(r#""message":"second","user":"alice","hash":""#));
+        assert_eq!(verify(&lines), Ok(3));
+        assert_eq!(verify(&[]), Ok(0));
+    }
+
+    #[test]
+    fn should_report_edited_record() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut lines = trail();
+        lines[1] = lines[1].replace("alice", "mallory");
+        assert_eq!(
+            verify(&lines).unwrap_err().to_string(),
+            "invalid audit log at line 2: hash does not match the record"
+        );
+    }
+
+    #[test]
+    fn should_report_removed_and_reordered_records() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut lines = trail();
+        lines.remove(1);
+        assert_eq!(
+            verify(&lines).unwrap_err(),
+            AuditError::new(2, "expected seq 2 but found 3")
+        );
+
+        let mut lines = trail();
+        lines.remove(0);
+        assert_eq!(verify(&lines).unwrap_err().line(), 1);
+
+        // only the seq or hash of the last record, kept elsewhere, shows this one
+        let mut lines = trail();
+        lines.pop();
+        assert_eq!(verify(&lines), Ok(2));
+
+        let mut lines = trail();
+        lines.push(String::from("not json"));
+        assert_eq!(
+            verify(&lines).unwrap_err(),
+            AuditError::new(4, "not an audit record")
+        );
+    }
+
+    #[test]
+    fn should_report_relinked_record() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut lines = trail();
+        (lines[1], _) = link(2, AUDIT_GENESIS, r#"{"message":"forged"}"#);
+        assert_eq!(
+            verify(&lines).unwrap_err(),
+            AuditError::new(2, "prev does not match the hash of the record before it")
+        );
+    }
+
+    #[test]
+    fn should_take_records_below_the_logger_level() {
+        if !static_level::enabled(LogLevel::Debug) {
+            return;
+        }
+        let output = Output::default();
+        Log::new_ex()
+            .set_log_level(LogLevel::Fatal)
+            .set_sink(AuditSink::new(output.clone()))
+            .done()
+            .debug("below");
+        let written = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(written.contains(r#""message":"below""#), "{written}");
+    }
+
+    #[test]
+    fn should_remove_unfinished_last_line_when_reopened() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_audit_torn_{}.log", process::id()));
+        let mut lines = trail();
+        let torn = lines[2][..20].to_string();
+        lines.truncate(2);
+        fs::write(&path, format!("{}\n{torn}", lines.join("\n"))).unwrap();
+        Log::new_ex()
+            .set_sink(AuditSink::open(&path).unwrap())
+            .done()
+            .error("after");
+        let verified = verify_audit_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(verified, Ok(3));
+    }
+
+    #[test]
+    fn should_keep_last_record_without_newline_when_reopened() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_audit_unfinished_{}.log", process::id()));
+        fs::write(&path, trail().join("\n")).unwrap();
+        Log::new_ex()
+            .set_sink(AuditSink::open(&path).unwrap())
+            .done()
+            .error("after");
+        let verified = verify_audit_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(verified, Ok(4));
+    }
+
+    #[test]
+    fn should_prefix_fields_named_after_link_keys() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let output = Output::default();
+        Log::new_ex()
+            .set_sink(AuditSink::new(output.clone()))
+            .done()
+            .child_with_field("seq", "7")
+            .child_with_field("hash", "forged")
+            .error("fields");
+        let written = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            written.contains(r#""fields.seq":"7","fields.hash":"forged","hash":""#),
+            "{written}"
+        );
+        assert_eq!(verify_audit(written.as_bytes()), Ok(1));
+    }
+
+    #[test]
+    fn should_continue_chain_when_reopened() {
+        if !static_level::enabled(LogLevel::Info) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_audit_{}.log", process::id()));
+        let _ = fs::remove_file(&path);
+        for msg in ["first", "second"] {
+            Log::new_ex()
+                .set_sink(AuditSink::open(&path).unwrap())
+                .done()
+                .info(msg);
+        }
+        let verified = verify_audit_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(verified, Ok(2));
+        assert_eq!(verify_audit_file(&path).unwrap_err().line(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::io::{self, Read};
+use crate::log::{Log, LogLevel, LogWriter};
+use crate::panic::Location;
+use crate::path::Path;
+use crate::process::{Command, ExitStatus, Stdio};
+use crate::thread;
+use crate::time::Instant;
+
+/// Runs a command, logging its output through the logger.
+///
+/// Output is logged by a child logger prefixed with the program name, each line of stdout at
+/// LogLevel::Info and each line of stderr at LogLevel::Warn. Once the command exits its status
+/// and duration are logged, at LogLevel::Info if it succeeded and LogLevel::Error otherwise.
+/// Returns the exit status, or the error, also logged, if the command couldn't be started or
+/// waited for.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use std_ex::log::{self, Log};
+///
+/// let log = Log::new();
+/// let status = log::run_command(&log, Command::new("ls")).unwrap();
+/// ```
+#[track_caller]
+pub fn run_command(log: &Log, mut command: Command) -> io::Result<ExitStatus> {
+    run(log, &mut command, Location::caller())
+}
+
+/// Extension trait for std::process::Command to run it with its output logged.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use std_ex::log::{CommandExt, Log};
+///
+/// let log = Log::new();
+/// let status = Command::new("ls").arg("-l").log_output(&log).unwrap();
+/// ```
+pub trait CommandExt {
+    /// Run the command, logging its output through the logger. See run_command.
+    fn log_output(&mut self, log: &Log) -> io::Result<ExitStatus>;
+}
+
+impl CommandExt for Command {
+    #[track_caller]
+    fn log_output(&mut self, log: &Log) -> io::Result<ExitStatus> {
+        run(log, self, Location::caller())
+    }
+}
+
+fn run(
+    log: &Log,
+    command: &mut Command,
+    location: &'static Location<'static>,
+) -> io::Result<ExitStatus> {
+    let program = Path::new(command.get_program())
+        .file_name()
+        .unwrap_or(command.get_program())
+        .to_string_lossy()
+        .to_string();
+    let log = log.child(Some(&format!("{program}:")));
+
+    let start = Instant::now();
+    let mut child = match command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) => {
+            let msg = format!("failed to start: {error}");
+            log.logger_at(LogLevel::Error, &msg, None, location);
+            return Err(error);
+        }
+    };
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let waited = thread::scope(|scope| {
+        scope.spawn(|| {
+            copy(
+                stdout,
+                LogWriter::new(log.clone(), LogLevel::Info, location),
+            )
+        });
+        scope.spawn(|| {
+            copy(
+                stderr,
+                LogWriter::new(log.clone(), LogLevel::Warn, location),
+            )
+        });
+        child.wait()
+    });
+    let status = match waited {
+        Ok(status) => status,
+        Err(error) => {
+            let msg = format!("failed to wait after {:?}: {error}", start.elapsed());
+            log.logger_at(LogLevel::Error, &msg, None, location);
+            return Err(error);
+        }
+    };
+
+    let msg = format!("exited with {status} after {:?}", start.elapsed());
+    let level = match status.success() {
+        true => LogLevel::Info,
+        false => LogLevel::Error,
+    };
+    log.logger_at(level, &msg, None, location);
+
+    Ok(status)
+}
+
+/// Copy a pipe into a writer until it closes, the writer logs the tail when dropped.
+fn copy<R: Read>(pipe: Option<R>, mut writer: LogWriter) {
+    if let Some(mut pipe) = pipe {
+        let _ = io::copy(&mut pipe, &mut writer);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod command_tests {
+    use super::*;
+    use crate::log::{Capture, static_level};
+    use crate::sync::Arc;
+
+    #[test]
+    fn should_log_stdout_stderr_and_exit_status() {
+        if !static_level::enabled(LogLevel::Info) {
+            return;
+        }
+        let capture = Arc::new(Capture::default());
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo out; echo err >&2; exit 3"]);
+        let status = run_command(&capture.logger(), command).unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let output = capture.formatted();
+        assert_eq!(output.len(), 3);
+        assert!(output.contains(&String::from("INFO: sh: out")));
+        assert!(output.contains(&String::from("WARN: sh: err")));
+        assert!(output[2].starts_with("ERROR: sh: exited with exit status: 3 after "));
+    }
+
+    #[test]
+    fn should_log_success_at_info_using_extension_trait() {
+        if !static_level::enabled(LogLevel::Info) {
+            return;
+        }
+        let capture = Arc::new(Capture::default());
+        let status = Command::new("/bin/sh")
+            .args(["-c", "printf 'no newline'"])
+            .log_output(&capture.logger())
+            .unwrap();
+        assert!(status.success());
+
+        let output = capture.formatted();
+        assert_eq!(output[0], "INFO: sh: no newline");
+        assert!(output[1].starts_with("INFO: sh: exited with exit status: 0 after "));
+    }
+
+    #[test]
+    fn should_log_and_return_spawn_errors() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let capture = Arc::new(Capture::default());
+        let result = run_command(&capture.logger(), Command::new("std_ex_missing_program"));
+        assert!(result.is_err());
+        assert!(
+            capture.formatted()[0].starts_with("ERROR: std_ex_missing_program: failed to start: ")
+        );
+    }
+}
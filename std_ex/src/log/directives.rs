@@ -0,0 +1,101 @@
+use crate::log::{LogLevel, ParseLogLevelError};
+use crate::str::FromStr;
+
+/// Log levels for named loggers, parsed from a comma separated list such as
+/// `warn,app.db=debug,app.db.pool=trace`.
+///
+/// A bare level applies to every logger, and `target=level` to the named logger with that target
+/// and all of its descendants. The most specific matching target wins.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::{LevelDirectives, LogLevel};
+///
+/// let directives: LevelDirectives = "warn,app.db=debug".parse().unwrap_or_default();
+/// assert_eq!(directives.level_for("app.db.pool"), Some(LogLevel::Debug));
+/// assert_eq!(directives.level_for("app.http"), Some(LogLevel::Warn));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelDirectives {
+    default: Option<LogLevel>,
+    targets: Vec<(String, LogLevel)>,
+}
+
+impl LevelDirectives {
+    /// Set the level for a target and its descendants.
+    pub fn set_target_level(&mut self, target: &str, level: LogLevel) {
+        self.targets.retain(|(existing, _)| existing != target);
+        self.targets.push((target.to_string(), level));
+    }
+
+    /// The level for a target from the most specific matching directive, None if none match.
+    pub fn level_for(&self, target: &str) -> Option<LogLevel> {
+        self.targets
+            .iter()
+            .filter(|(directive, _)| is_descendant(target, directive))
+            .max_by_key(|(directive, _)| directive.len())
+            .map(|(_, level)| *level)
+            .or(self.default)
+    }
+}
+
+impl FromStr for LevelDirectives {
+    type Err = ParseLogLevelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut directives = LevelDirectives::default();
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    directives.set_target_level(target.trim(), level.trim().parse()?)
+                }
+                None => directives.default = Some(directive.parse()?),
+            }
+        }
+
+        Ok(directives)
+    }
+}
+
+/// Is the target the directive's target, or nested beneath it.
+fn is_descendant(target: &str, directive: &str) -> bool {
+    match target.strip_prefix(directive) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod directives_tests {
+    use super::*;
+
+    fn directives(s: &str) -> LevelDirectives {
+        s.parse()
+            .unwrap_or_else(|_| panic!("invalid directives {s}"))
+    }
+
+    #[test]
+    fn should_parse_a_bare_level_as_default() {
+        assert_eq!(directives("debug").level_for("any"), Some(LogLevel::Debug));
+        assert_eq!(directives("").level_for("any"), None);
+    }
+
+    #[test]
+    fn should_prefer_most_specific_target() {
+        let directives = directives("app.db.pool=trace, warn ,app.db=debug");
+        assert_eq!(
+            directives.level_for("app.db.pool.conn"),
+            Some(LogLevel::Trace)
+        );
+        assert_eq!(directives.level_for("app.db"), Some(LogLevel::Debug));
+        assert_eq!(directives.level_for("app.dbx"), Some(LogLevel::Warn));
+        assert_eq!(directives.level_for(""), Some(LogLevel::Warn));
+    }
+
+    #[test]
+    fn should_reject_invalid_levels() {
+        assert!("app=loud".parse::<LevelDirectives>().is_err());
+        assert!("loud".parse::<LevelDirectives>().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::chrono;
+use crate::log::Record;
+use crate::sync::{Mutex, MutexGuard};
+
+/// What to do with a record when collapsing consecutive duplicates.
+pub(crate) enum Repeat {
+    /// The record differs from the last, and the last wasn't repeated.
+    New,
+    /// The record is the same as the last, so is dropped.
+    Duplicate,
+    /// The record differs from the last, which was repeated, so a summary is written first.
+    Ended(Box<Summary>),
+}
+
+/// `last message repeated N times`, from the repeated record's level, call site, target, prefix,
+/// context and fields.
+pub(crate) struct Summary {
+    pub(crate) record: Record,
+    /// Whether the logger wrote the repeated record, rather than only sinks with their own level.
+    pub(crate) enabled: bool,
+}
+
+struct Last {
+    record: Record,
+    enabled: bool,
+    count: u64,
+}
+
+impl Last {
+    fn summary(self) -> Option<Summary> {
+        if self.count == 0 {
+            return None;
+        }
+        let mut record = self.record;
+        record.time = chrono::Local::now();
+        record.msg = summary(self.count);
+        record.data = None;
+        record.errors.clear();
+        record.backtrace = None;
+        Some(Summary {
+            record,
+            enabled: self.enabled,
+        })
+    }
+}
+
+/// Tracks the last record logged, to collapse runs of identical records.
+pub(crate) struct Duplicates {
+    last: Mutex<Option<Last>>,
+}
+
+impl Duplicates {
+    pub(crate) fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Compare the record with the last one, remembering it if it is different.
+    pub(crate) fn check(&self, record: &Record, enabled: bool) -> Repeat {
+        let mut last = self.lock();
+        if let Some(last) = last.as_mut()
+            && is_repeat(&last.record, record)
+        {
+            last.count += 1;
+            return Repeat::Duplicate;
+        }
+
+        let previous = last.replace(Last {
+            record: record.clone(),
+            enabled,
+            count: 0,
+        });
+        match previous.and_then(Last::summary) {
+            Some(summary) => Repeat::Ended(Box::new(summary)),
+            None => Repeat::New,
+        }
+    }
+
+    /// The summary of a run of duplicates which hasn't ended yet, forgetting the last record.
+    pub(crate) fn finish(&self) -> Option<Summary> {
+        self.lock().take().and_then(Last::summary)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Last>> {
+        self.last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Is the record the same as the last, other than when and where it was logged.
+fn is_repeat(last: &Record, record: &Record) -> bool {
+    last.level == record.level
+        && last.target == record.target
+        && last.prefix == record.prefix
+        && last.msg == record.msg
+        && last.data == record.data
+        && last.errors == record.errors
+        && last.context == record.context
+        && last.fields == record.fields
+}
+
+/// The message written when a run of duplicates ends.
+pub(crate) fn summary(count: u64) -> String {
+    match count {
+        1 => String::from("last message repeated 1 time"),
+        count => format!("last message repeated {count} times"),
+    }
+}
+
+#[cfg(test)]
+mod duplicates_tests {
+    use super::*;
+    use crate::log::LogLevel;
+    use crate::panic::Location;
+
+    /// The summary's message and count, or None for new records and duplicates.
+    fn ended(repeat: Repeat) -> Option<(String, LogLevel, &'static Location<'static>)> {
+        match repeat {
+            Repeat::Ended(summary) => Some((
+                summary.record.msg,
+                summary.record.level,
+                summary.record.location,
+            )),
+            _ => None,
+        }
+    }
+
+    fn is_new(repeat: Repeat) -> bool {
+        matches!(repeat, Repeat::New)
+    }
+
+    #[test]
+    fn should_collapse_consecutive_duplicates() {
+        let duplicates = Duplicates::new();
+        let retry = Record::test(LogLevel::Warn, "retrying");
+        assert!(is_new(duplicates.check(&retry, true)));
+        assert!(matches!(duplicates.check(&retry, true), Repeat::Duplicate));
+        assert!(matches!(duplicates.check(&retry, true), Repeat::Duplicate));
+        assert_eq!(
+            ended(duplicates.check(&Record::test(LogLevel::Info, "done"), true)),
+            Some((
+                String::from("last message repeated 2 times"),
+                LogLevel::Warn,
+                retry.location
+            ))
+        );
+        assert!(is_new(duplicates.check(&retry, true)));
+    }
+
+    #[test]
+    fn should_treat_other_levels_and_data_as_different() {
+        let duplicates = Duplicates::new();
+        let mut with_data = Record::test(LogLevel::Warn, "retrying");
+        with_data.data = Some(String::from("1"));
+        assert!(is_new(
+            duplicates.check(&Record::test(LogLevel::Warn, "retrying"), true)
+        ));
+        assert!(is_new(
+            duplicates.check(&Record::test(LogLevel::Error, "retrying"), true)
+        ));
+        assert!(is_new(duplicates.check(&with_data, true)));
+    }
+
+    #[test]
+    fn should_treat_other_targets_prefixes_and_fields_as_different() {
+        let duplicates = Duplicates::new();
+        let mut db = Record::test(LogLevel::Warn, "retrying");
+        db.target = String::from("app.db");
+        let mut child = Record::test(LogLevel::Warn, "retrying");
+        child.prefix = String::from(" child");
+        let mut request = Record::test(LogLevel::Warn, "retrying");
+        request.fields = vec![(String::from("request_id"), String::from("1"))];
+        for record in [Record::test(LogLevel::Warn, "retrying"), db, child, request] {
+            assert!(is_new(duplicates.check(&record, true)));
+        }
+    }
+
+    #[test]
+    fn should_summarise_from_the_repeated_record() {
+        let duplicates = Duplicates::new();
+        let mut retry = Record::test(LogLevel::Warn, "retrying");
+        retry.target = String::from("app.db");
+        retry.prefix = String::from(" pool");
+        duplicates.check(&retry, false);
+        duplicates.check(&retry, false);
+        let Repeat::Ended(summary) = duplicates.check(&Record::test(LogLevel::Info, "done"), true)
+        else {
+            panic!("expected the run of duplicates to end");
+        };
+        assert_eq!(summary.record.target, "app.db");
+        assert_eq!(summary.record.prefix, " pool");
+        assert!(!summary.enabled);
+    }
+
+    #[test]
+    fn should_finish_a_pending_run_of_duplicates() {
+        let duplicates = Duplicates::new();
+        let retry = Record::test(LogLevel::Warn, "retrying");
+        duplicates.check(&retry, true);
+        assert!(duplicates.finish().is_none());
+        duplicates.check(&retry, true);
+        duplicates.check(&retry, true);
+        let summary = duplicates.finish().unwrap();
+        assert_eq!(summary.record.msg, "last message repeated 1 time");
+        assert!(duplicates.finish().is_none());
+    }
+
+    #[test]
+    fn should_write_summary() {
+        assert_eq!(summary(1), "last message repeated 1 time");
+        assert_eq!(summary(5000), "last message repeated 5000 times");
+    }
+}
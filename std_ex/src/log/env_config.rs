@@ -0,0 +1,210 @@
+use crate::log::{FileSink, Format, LevelDirectives, LogrsBuilder, Timestamp};
+use crate::term::ColourPolicy;
+use crate::{error::Error, fmt, fmt::Display};
+
+pub(crate) const LOG_LEVEL_ENV_VAR_NAME: &str = "LOG_LEVEL";
+pub(crate) const LOG_FORMAT_ENV_VAR_NAME: &str = "LOG_FORMAT";
+pub(crate) const LOG_TIMESTAMP_ENV_VAR_NAME: &str = "LOG_TIMESTAMP";
+pub(crate) const LOG_COLOR_ENV_VAR_NAME: &str = "LOG_COLOR";
+pub(crate) const LOG_FILE_ENV_VAR_NAME: &str = "LOG_FILE";
+
+/// Why the logger couldn't be configured from the environment, listing every invalid variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvConfigError {
+    reason: String,
+}
+
+impl EnvConfigError {
+    fn new<T: Into<String>>(reason: T) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for EnvConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid log environment: {}", self.reason)
+    }
+}
+
+impl Error for EnvConfigError {}
+
+/// Configure the builder from the variables, see LogrsBuilder::configure_from_env.
+/// Nothing is changed unless every variable set is valid.
+pub(crate) fn configure(
+    builder: &mut LogrsBuilder,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<(), EnvConfigError> {
+    let var = |key: &str| var(key).filter(|value| !value.trim().is_empty());
+    let mut reasons = Vec::new();
+    let mut parse = |key: &str, parse: &dyn Fn(&str) -> Result<_, String>| {
+        let value = var(key)?;
+        parse(value.trim())
+            .map_err(|reason| reasons.push(format!("{key}={value:?} {reason}")))
+            .ok()
+    };
+
+    let level_directives = parse(LOG_LEVEL_ENV_VAR_NAME, &|value| {
+        parse_level_directives(value).map(Setting::Level)
+    });
+    let format = parse(
+        LOG_FORMAT_ENV_VAR_NAME,
+        &|value| match value.to_lowercase().as_str() {
+            "text" => Ok(Setting::Format(Format::Text)),
+            "json" => Ok(Setting::Format(Format::Json)),
+            "logfmt" => Ok(Setting::Format(Format::Logfmt)),
+            _ => Err(String::from("is not one of text, json or logfmt")),
+        },
+    );
+    let timestamp = parse(
+        LOG_TIMESTAMP_ENV_VAR_NAME,
+        &|value| match value.to_lowercase().as_str() {
+            "none" => Ok(Setting::Timestamp(Timestamp::None)),
+            "time" => Ok(Setting::Timestamp(Timestamp::Time)),
+            "datetime" => Ok(Setting::Timestamp(Timestamp::DateAndTime)),
+            _ => Err(String::from("is not one of none, time or datetime")),
+        },
+    );
+    let colour_policy = parse(
+        LOG_COLOR_ENV_VAR_NAME,
+        &|value| match value.to_lowercase().as_str() {
+            "auto" => Ok(Setting::Colour(ColourPolicy::Auto)),
+            "always" => Ok(Setting::Colour(ColourPolicy::Always)),
+            "never" => Ok(Setting::Colour(ColourPolicy::Never)),
+            _ => Err(String::from("is not one of auto, always or never")),
+        },
+    );
+    let file = parse(LOG_FILE_ENV_VAR_NAME, &|value| {
+        FileSink::open(value)
+            .map(Setting::File)
+            .map_err(|error| format!("could not be opened, {error}"))
+    });
+
+    if !reasons.is_empty() {
+        return Err(EnvConfigError::new(reasons.join(", ")));
+    }
+
+    // a file is never a terminal, so only colour it when asked to
+    let colour_policy = match (colour_policy, &file) {
+        (None, Some(_)) => Some(Setting::Colour(ColourPolicy::Never)),
+        (colour_policy, _) => colour_policy,
+    };
+    let settings = [level_directives, format, timestamp, colour_policy, file];
+    for setting in settings.into_iter().flatten() {
+        match setting {
+            Setting::Level(level_directives) => builder.set_level_directives(level_directives),
+            Setting::Format(format) => builder.set_format(format),
+            Setting::Timestamp(timestamp) => builder.set_timestamp(timestamp),
+            Setting::Colour(colour_policy) => builder.set_colour_policy(colour_policy),
+            Setting::File(file) => builder.set_sink(file),
+        };
+    }
+    Ok(())
+}
+
+/// LOG_LEVEL, read once as each logger is created, None if it isn't set.
+/// Returns the same error as configure would for an invalid value.
+pub(crate) fn level_directives(
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Option<LevelDirectives>, EnvConfigError> {
+    let Some(value) = var(LOG_LEVEL_ENV_VAR_NAME).filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+    parse_level_directives(value.trim())
+        .map(Some)
+        .map_err(|reason| {
+            EnvConfigError::new(format!("{LOG_LEVEL_ENV_VAR_NAME}={value:?} {reason}"))
+        })
+}
+
+fn parse_level_directives(value: &str) -> Result<LevelDirectives, String> {
+    value
+        .parse::<LevelDirectives>()
+        .map_err(|error| format!("is an {error}"))
+}
+
+/// A valid setting read from one of the variables.
+enum Setting {
+    Level(LevelDirectives),
+    Format(Format),
+    Timestamp(Timestamp),
+    Colour(ColourPolicy),
+    File(FileSink),
+}
+
+#[cfg(test)]
+mod env_config_tests {
+    use super::*;
+    use crate::collections::HashMap;
+    use crate::log::{Log, LogLevel, static_level};
+    use crate::{env, fs, process};
+
+    fn configure_with(vars: &[(&str, &str)]) -> Result<LogrsBuilder, EnvConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let mut builder = Log::new_ex();
+        configure(&mut builder, |key| vars.get(key).cloned())?;
+        Ok(builder)
+    }
+
+    #[test]
+    fn should_accept_no_variables() {
+        assert!(configure_with(&[]).is_ok());
+        assert!(configure_with(&[(LOG_FORMAT_ENV_VAR_NAME, " ")]).is_ok());
+    }
+
+    #[test]
+    fn should_report_every_invalid_variable() {
+        let error = configure_with(&[
+            (LOG_LEVEL_ENV_VAR_NAME, "info,app=loud"),
+            (LOG_FORMAT_ENV_VAR_NAME, "xml"),
+            (LOG_TIMESTAMP_ENV_VAR_NAME, "time"),
+            (LOG_COLOR_ENV_VAR_NAME, "sometimes"),
+        ])
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid log environment: LOG_LEVEL=\"info,app=loud\" is an invalid log level, \
+             expected one of trace, debug, info, warn, error, fatal or silent, \
+             LOG_FORMAT=\"xml\" is not one of text, json or logfmt, \
+             LOG_COLOR=\"sometimes\" is not one of auto, always or never"
+        );
+    }
+
+    #[test]
+    fn should_report_file_which_cannot_be_opened() {
+        let error = configure_with(&[(LOG_FILE_ENV_VAR_NAME, "/")])
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid log environment: LOG_FILE=\"/\" could not be opened, ")
+        );
+    }
+
+    #[test]
+    fn should_write_to_file_in_format_without_colour() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_env_config_{}.log", process::id()));
+        let _ = fs::remove_file(&path);
+        configure_with(&[
+            (LOG_LEVEL_ENV_VAR_NAME, "error"),
+            (LOG_FORMAT_ENV_VAR_NAME, "LogFmt"),
+            (LOG_TIMESTAMP_ENV_VAR_NAME, "none"),
+            (LOG_FILE_ENV_VAR_NAME, &path.to_string_lossy()),
+        ])
+        .unwrap()
+        .done()
+        .error("\x1b[31mwritten\x1b[0m");
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(written.ends_with(" level=error msg=written\n"), "{written}");
+    }
+}
@@ -0,0 +1,87 @@
+use crate::error::Error;
+
+/// The error's message followed by the message of each error in its source chain.
+pub(crate) fn chain(error: &dyn Error) -> Vec<String> {
+    let mut errors = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(error) = source {
+        errors.push(error.to_string());
+        source = error.source();
+    }
+    errors
+}
+
+/// Render an error chain as text, the error on the first line and an indented `caused by:` line
+/// for each of its sources.
+pub(crate) fn text(errors: &[String]) -> String {
+    let mut output = String::new();
+    for (i, error) in errors.iter().enumerate() {
+        match i {
+            0 => output.push_str("error: "),
+            _ => output.push_str("\n  caused by: "),
+        }
+        output.push_str(error);
+    }
+    output
+}
+
+#[cfg(test)]
+mod error_chain_tests {
+    use super::*;
+    use crate::fmt;
+
+    #[derive(Debug)]
+    struct Failed {
+        msg: &'static str,
+        source: Option<Box<Failed>>,
+    }
+
+    impl fmt::Display for Failed {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.msg)
+        }
+    }
+
+    impl Error for Failed {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.source
+                .as_deref()
+                .map(|source| source as &(dyn Error + 'static))
+        }
+    }
+
+    fn failed(msg: &'static str, source: Option<Failed>) -> Failed {
+        Failed {
+            msg,
+            source: source.map(Box::new),
+        }
+    }
+
+    #[test]
+    fn should_follow_every_source() {
+        let error = failed(
+            "reading config",
+            Some(failed(
+                "opening file",
+                Some(failed("permission denied", None)),
+            )),
+        );
+        assert_eq!(
+            chain(&error),
+            vec!["reading config", "opening file", "permission denied"]
+        );
+    }
+
+    #[test]
+    fn should_indent_causes() {
+        let errors = chain(&failed(
+            "reading config",
+            Some(failed("permission denied", None)),
+        ));
+        assert_eq!(
+            text(&errors),
+            "error: reading config\n  caused by: permission denied"
+        );
+        assert_eq!(text(&[]), "");
+    }
+}
@@ -0,0 +1,73 @@
+use crate::fs::{File, OpenOptions};
+use crate::io::{self, Write};
+use crate::log::{Record, Sink, count_dropped};
+use crate::path::Path;
+use crate::sync::Mutex;
+
+/// A sink appending each formatted record as a line to a file.
+///
+/// The file is created if it doesn't exist, and opened in append mode so several processes can
+/// share it. Records which fail to write are counted as dropped.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std_ex::log::{FileSink, Log};
+///
+/// let log = Log::new_ex()
+///     .disable_ansi()
+///     .set_sink(FileSink::open("app.log").unwrap())
+///     .done();
+/// log.info("written to app.log");
+/// ```
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    /// Open the file for appending, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Sink for FileSink {
+    fn log(&self, record: &Record, formatted: &str) {
+        let mut file = self
+            .file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if writeln!(file, "{formatted}").is_err() {
+            count_dropped(record.level, &record.target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::*;
+    use crate::log::{Log, LogLevel, static_level};
+    use crate::{env, fs, process};
+
+    #[test]
+    fn should_append_each_log_as_a_line() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_file_sink_{}.log", process::id()));
+        let _ = fs::remove_file(&path);
+        for msg in ["first", "second"] {
+            Log::new_ex()
+                .disable_ansi()
+                .set_sink(FileSink::open(&path).unwrap())
+                .done()
+                .error(msg);
+        }
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written, "ERROR: first\nERROR: second\n");
+    }
+}
@@ -0,0 +1,434 @@
+use crate::borrow::Cow;
+use crate::chrono::{SecondsFormat, Utc};
+use crate::fmt::Write;
+use crate::log::Record;
+
+/// Keys written by Format::Json, which fields can't use.
+const JSON_KEYS: [&str; 9] = [
+    "timestamp",
+    "level",
+    "message",
+    "target",
+    "prefix",
+    "data",
+    "errors",
+    "backtrace",
+    "context",
+];
+/// Keys written by Format::Logfmt, which fields can't use.
+const LOGFMT_KEYS: [&str; 9] = [
+    "time",
+    "level",
+    "msg",
+    "target",
+    "prefix",
+    "data",
+    "errors",
+    "backtrace",
+    "context",
+];
+/// Leads the key of a field named after one of the keys above, e.g. `fields.message`, or
+/// already starting with it, e.g. `fields.fields.message`, so no two fields share a key.
+const RESERVED_FIELD_PREFIX: &str = "fields.";
+
+/// How each log record is written.
+/// Format::Text -> Laid out by the configured Template, e.g. `INFO: prefix message`.
+/// Format::Json -> One JSON object per line with `timestamp`, `level` and `message` keys, the
+/// shape aws lambda expects when AWS_LAMBDA_LOG_FORMAT=JSON, followed by `target`, `prefix`,
+/// `data`, `errors`, `backtrace`, `context` and any fields. `errors` is an array of the error
+/// logged by error_chain followed by each of its sources.
+/// Format::Logfmt -> One line of space separated `key=value` pairs, with `time`, `level` and
+/// `msg` keys followed by the same keys as JSON, and the error chain joined by `: `. Values are
+/// quoted when they contain spaces, quotes, `=` or control characters, and escaped as in JSON.
+/// Those characters are replaced with `_` in field names.
+/// In both, a field named after one of the format's own keys, or starting with `fields.`, is
+/// written as `fields.<key>`, e.g. `fields.message`, so no key is written twice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Logfmt,
+}
+
+impl Format {
+    /// Is this a machine readable format, where ansi codes are never wanted.
+    pub(crate) fn is_structured(&self) -> bool {
+        !matches!(self, Format::Text)
+    }
+}
+
+/// How line breaks in text logs are written, including those from the pretty printed data of
+/// logf, logp and context.
+/// Multiline::Preserve -> Line breaks are written as is.
+/// Multiline::Escape -> Line breaks are escaped as `\n` and `\r`, so every log is a single line
+/// for line oriented collectors such as CloudWatch, journald or grep. Backslashes are escaped as
+/// `\\` first, so the escaping can be reversed.
+/// Multiline::Indent(marker) -> Every continuation line starts with the marker, e.g. `"  | "`, so
+/// it can be told apart from the start of the next log. `\r\n` and a lone `\r` both count as a
+/// line break.
+/// JSON output is always a single line, so this only applies to Format::Text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Multiline {
+    #[default]
+    Preserve,
+    Escape,
+    Indent(String),
+}
+
+impl Multiline {
+    /// Apply to a rendered log.
+    pub(crate) fn apply<'a>(&self, log: &'a str) -> Cow<'a, str> {
+        match self {
+            Multiline::Preserve => Cow::Borrowed(log),
+            Multiline::Escape if !log.contains(['\n', '\r', '\\']) => Cow::Borrowed(log),
+            Multiline::Indent(_) if !log.contains(['\n', '\r']) => Cow::Borrowed(log),
+            Multiline::Escape => Cow::Owned(
+                log.replace('\\', "\\\\")
+                    .replace('\r', "\\r")
+                    .replace('\n', "\\n"),
+            ),
+            Multiline::Indent(marker) => Cow::Owned(
+                log.replace("\r\n", "\n")
+                    .replace('\r', "\n")
+                    .replace('\n', &format!("\n{marker}")),
+            ),
+        }
+    }
+}
+
+/// Render a record as a single line JSON object.
+pub(crate) fn json(record: &Record) -> String {
+    json_reserving(record, &[])
+}
+
+/// Render a record as a single line JSON object, prefixing fields named after any of the keys
+/// the caller adds to it as well as the format's own.
+pub(crate) fn json_reserving(record: &Record, keys: &[&str]) -> String {
+    let mut output = String::from("{");
+    let timestamp = record
+        .time
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true);
+    write_json_pair(&mut output, "timestamp", &timestamp);
+    write_json_pair(
+        &mut output,
+        "level",
+        &record.level.to_string().to_uppercase(),
+    );
+    write_json_pair(&mut output, "message", &record.msg);
+
+    if !record.target.is_empty() {
+        write_json_pair(&mut output, "target", &record.target);
+    }
+    let prefix = record.prefix.trim();
+    if !prefix.is_empty() {
+        write_json_pair(&mut output, "prefix", prefix);
+    }
+    if let Some(data) = &record.data {
+        write_json_pair(&mut output, "data", data);
+    }
+    if !record.errors.is_empty() {
+        output.push_str(",\"errors\":[");
+        for (i, error) in record.errors.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            write_json_string(&mut output, error);
+        }
+        output.push(']');
+    }
+    if let Some(backtrace) = &record.backtrace {
+        write_json_pair(&mut output, "backtrace", backtrace);
+    }
+    if !record.context.is_empty() {
+        write_json_pair(&mut output, "context", &record.context);
+    }
+    for (key, value) in &record.fields {
+        write_json_pair(&mut output, &field_key(key, &JSON_KEYS, keys), value);
+    }
+
+    output.push('}');
+    output
+}
+
+/// Render a record as a single line of logfmt.
+pub(crate) fn logfmt(record: &Record) -> String {
+    let mut output = String::new();
+    let time = record
+        .time
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true);
+    write_logfmt_pair(&mut output, "time", &time);
+    write_logfmt_pair(
+        &mut output,
+        "level",
+        &record.level.to_string().to_lowercase(),
+    );
+    write_logfmt_pair(&mut output, "msg", &record.msg);
+
+    if !record.target.is_empty() {
+        write_logfmt_pair(&mut output, "target", &record.target);
+    }
+    let prefix = record.prefix.trim();
+    if !prefix.is_empty() {
+        write_logfmt_pair(&mut output, "prefix", prefix);
+    }
+    if let Some(data) = &record.data {
+        write_logfmt_pair(&mut output, "data", data);
+    }
+    if !record.errors.is_empty() {
+        write_logfmt_pair(&mut output, "errors", &record.errors.join(": "));
+    }
+    if let Some(backtrace) = &record.backtrace {
+        write_logfmt_pair(&mut output, "backtrace", backtrace);
+    }
+    if !record.context.is_empty() {
+        write_logfmt_pair(&mut output, "context", &record.context);
+    }
+    for (key, value) in &record.fields {
+        let key = logfmt_key(key);
+        write_logfmt_pair(&mut output, &field_key(&key, &LOGFMT_KEYS, &[]), value);
+    }
+    output
+}
+
+/// The key a field is written with, prefixed if the format or the caller uses the key itself.
+fn field_key<'a>(key: &'a str, reserved: &[&str], keys: &[&str]) -> Cow<'a, str> {
+    match reserved.contains(&key) || keys.contains(&key) || key.starts_with(RESERVED_FIELD_PREFIX) {
+        true => Cow::Owned(format!("{RESERVED_FIELD_PREFIX}{key}")),
+        false => Cow::Borrowed(key),
+    }
+}
+
+/// A field name usable as a logfmt key, with `_` for the characters which would end it.
+fn logfmt_key(key: &str) -> Cow<'_, str> {
+    match key.chars().any(breaks_logfmt) {
+        true => Cow::Owned(key.replace(breaks_logfmt, "_")),
+        false if key.is_empty() => Cow::Borrowed("_"),
+        false => Cow::Borrowed(key),
+    }
+}
+
+/// Does the character end an unquoted logfmt key or value.
+fn breaks_logfmt(c: char) -> bool {
+    c.is_whitespace() || c.is_control() || c == '=' || c == '"'
+}
+
+fn write_logfmt_pair(output: &mut String, key: &str, value: &str) {
+    if !output.is_empty() {
+        output.push(' ');
+    }
+    output.push_str(key);
+    output.push('=');
+    match value.is_empty() || value.contains(breaks_logfmt) {
+        true => write_json_string(output, value),
+        false => output.push_str(value),
+    }
+}
+
+fn write_json_pair(output: &mut String, key: &str, value: &str) {
+    if !output.ends_with('{') {
+        output.push(',');
+    }
+    write_json_string(output, key);
+    output.push(':');
+    write_json_string(output, value);
+}
+
+/// Write a string as a quoted and escaped JSON string.
+pub(crate) fn write_json_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod multiline_tests {
+    use super::*;
+
+    const LOG: &str = "INFO: message\nData {\r\n    field: 1,\n}";
+
+    #[test]
+    fn preserve_should_leave_line_breaks() {
+        assert_eq!(Multiline::Preserve.apply(LOG), LOG);
+    }
+
+    #[test]
+    fn escape_should_write_a_single_line() {
+        assert_eq!(
+            Multiline::Escape.apply(LOG),
+            "INFO: message\\nData {\\r\\n    field: 1,\\n}"
+        );
+    }
+
+    #[test]
+    fn escape_should_escape_backslashes_first() {
+        assert_eq!(
+            Multiline::Escape.apply("path\\n \"a\\nb\"\nnext"),
+            "path\\\\n \"a\\\\nb\"\\nnext"
+        );
+        assert_eq!(
+            Multiline::Escape.apply("single line \\n"),
+            "single line \\\\n"
+        );
+        assert_eq!(Multiline::Escape.apply("single line"), "single line");
+    }
+
+    #[test]
+    fn indent_should_mark_continuation_lines() {
+        assert_eq!(
+            Multiline::Indent(String::from("  | ")).apply(LOG),
+            "INFO: message\n  | Data {\n  |     field: 1,\n  | }"
+        );
+        assert_eq!(
+            Multiline::Indent(String::from("  | ")).apply("progress\rdone"),
+            "progress\n  | done"
+        );
+    }
+}
+
+#[cfg(test)]
+mod logfmt_tests {
+    use super::*;
+    use crate::chrono::{self, TimeZone};
+    use crate::log::LogLevel;
+
+    #[test]
+    fn should_write_pairs_quoting_when_needed() {
+        let mut record = Record {
+            time: chrono::Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            target: String::from("app.db"),
+            data: Some(String::from("[\n    1,\n]")),
+            errors: vec![String::from("timed out"), String::from("reset")],
+            fields: vec![(String::from("query"), String::from("a=\"b\""))],
+            ..Record::test(LogLevel::Warn, "slow query")
+        };
+        let time = record
+            .time
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Millis, true);
+        assert_eq!(
+            logfmt(&record),
+            format!(
+                r#"time={time} level=warn msg="slow query" target=app.db data="[\n    1,\n]" errors="timed out: reset" query="a=\"b\"""#
+            )
+        );
+        record.msg = String::new();
+        assert!(logfmt(&record).contains(r#" msg="" "#));
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+    use crate::chrono::{self, TimeZone};
+    use crate::log::LogLevel;
+
+    fn record() -> Record {
+        Record {
+            time: chrono::Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            ..Record::test(LogLevel::Warn, "message")
+        }
+    }
+
+    #[test]
+    fn should_write_lambda_shape() {
+        let mut record = record();
+        record
+            .fields
+            .push((String::from("requestId"), String::from("abc-123")));
+        let timestamp = record
+            .time
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Millis, true);
+        assert_eq!(
+            json(&record),
+            format!(
+                r#"{{"timestamp":"{timestamp}","level":"WARN","message":"message","requestId":"abc-123"}}"#
+            )
+        );
+        assert!(timestamp.ends_with(".000Z"));
+    }
+
+    #[test]
+    fn should_include_optional_parts_only_when_present() {
+        let mut record = record();
+        record.target = String::from("app.db");
+        record.prefix = String::from(" child:");
+        record.data = Some(String::from("[1, 2]"));
+        let output = json(&record);
+        assert!(output.contains(r#""target":"app.db","prefix":"child:","data":"[1, 2]""#));
+        assert!(!output.contains("context"));
+        assert!(!output.contains("errors"));
+    }
+
+    #[test]
+    fn should_write_errors_as_array() {
+        let mut record = record();
+        record.errors = vec![String::from("reading config"), String::from("no \"file\"")];
+        assert!(
+            json(&record)
+                .ends_with(r#""message":"message","errors":["reading config","no \"file\""]}"#)
+        );
+    }
+
+    #[test]
+    fn should_prefix_fields_named_after_json_keys() {
+        let mut record = record();
+        record.fields = vec![
+            (String::from("message"), String::from("field")),
+            (String::from("msg"), String::from("kept")),
+        ];
+        assert!(
+            json(&record)
+                .ends_with(r#""message":"message","fields.message":"field","msg":"kept"}"#)
+        );
+        assert!(logfmt(&record).ends_with(" message=field fields.msg=kept"));
+    }
+
+    #[test]
+    fn should_prefix_fields_which_already_start_with_the_prefix() {
+        let mut record = record();
+        record.fields = vec![
+            (String::from("message"), String::from("renamed")),
+            (String::from("fields.message"), String::from("own")),
+        ];
+        assert!(
+            json(&record).ends_with(r#""fields.message":"renamed","fields.fields.message":"own"}"#)
+        );
+    }
+
+    #[test]
+    fn should_escape_logfmt_values_and_replace_key_breaking_characters() {
+        let mut record = record();
+        record.fields = vec![
+            (String::from("a b=c"), String::from("\u{1b}[31m\\ \"q\"\n")),
+            (String::new(), String::from("empty")),
+        ];
+        assert!(
+            logfmt(&record).ends_with(r#" a_b_c="\u001b[31m\\ \"q\"\n" _=empty"#),
+            "{}",
+            logfmt(&record)
+        );
+    }
+
+    #[test]
+    fn should_escape_strings() {
+        let mut output = String::new();
+        write_json_string(&mut output, "quote \" slash \\ line\nnull \u{0}");
+        assert_eq!(output, r#""quote \" slash \\ line\nnull \u0000""#);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::io;
+use crate::log::{Record, Sink, count_dropped, syslog, syslog_severity};
+use crate::os::unix::net::UnixDatagram;
+use crate::path::Path;
+
+/// The socket journald listens on for its native protocol.
+pub const JOURNALD_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+/// Fields journald gives a meaning to, which the logger's fields can't use.
+const RESERVED_FIELD_NAMES: [&str; 18] = [
+    "MESSAGE",
+    "MESSAGE_ID",
+    "PRIORITY",
+    "CODE_FILE",
+    "CODE_LINE",
+    "CODE_FUNC",
+    "ERRNO",
+    "INVOCATION_ID",
+    "USER_INVOCATION_ID",
+    "SYSLOG_FACILITY",
+    "SYSLOG_IDENTIFIER",
+    "SYSLOG_PID",
+    "SYSLOG_TIMESTAMP",
+    "SYSLOG_RAW",
+    "DOCUMENTATION",
+    "TID",
+    "UNIT",
+    "USER_UNIT",
+];
+/// Leads the name of a field which would be one of the names above, e.g. `FIELDS_MESSAGE`.
+const RESERVED_FIELD_PREFIX: &str = "FIELDS_";
+/// The longest field name journald accepts.
+const MAX_FIELD_NAME_LEN: usize = 64;
+
+/// A sink which sends each record to journald using its native protocol.
+///
+/// Each record is sent as `PRIORITY`, `MESSAGE`, `CODE_FILE`, `CODE_LINE` and
+/// `SYSLOG_IDENTIFIER` fields, followed by the logger's fields with their names converted to
+/// journald's uppercase form, e.g. `requestId` becomes `REQUESTID`. A field which would take one of
+/// journald's own names, such as `message`, is sent with a `FIELDS_` prefix, e.g. `FIELDS_MESSAGE`,
+/// so it can't replace the record's own. Values spanning several lines,
+/// such as data from logf, are sent using journald's length prefixed encoding so they stay a
+/// single entry.
+///
+/// Records too large for a single datagram are dropped, as passing them through a memfd is not
+/// supported, and counted by log::metrics.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std_ex::log::{JournaldSink, Log};
+///
+/// let log = Log::new_ex().set_sink(JournaldSink::new().unwrap()).done();
+/// log.info("sent to the journal");
+/// ```
+pub struct JournaldSink {
+    socket: UnixDatagram,
+    identifier: String,
+}
+
+impl JournaldSink {
+    /// Connect to journald at /run/systemd/journal/socket.
+    pub fn new() -> io::Result<Self> {
+        Self::with_path(JOURNALD_SOCKET_PATH)
+    }
+
+    /// Connect to journald listening on a different socket.
+    pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        let identifier = syslog::program_name().unwrap_or_default();
+        Ok(Self { socket, identifier })
+    }
+
+    /// Set the SYSLOG_IDENTIFIER field.
+    /// Default: The file name of the running program.
+    pub fn set_identifier<T: Into<String>>(mut self, identifier: T) -> Self {
+        self.identifier = identifier.into();
+        self
+    }
+
+    /// Encode a record as a journald native protocol datagram.
+    fn encode(&self, record: &Record) -> Vec<u8> {
+        let mut datagram = Vec::new();
+        encode_field(
+            &mut datagram,
+            "PRIORITY",
+            &syslog_severity(record.level).to_string(),
+        );
+        encode_field(&mut datagram, "MESSAGE", &syslog::message(record, false));
+        encode_field(&mut datagram, "CODE_FILE", record.location.file());
+        encode_field(
+            &mut datagram,
+            "CODE_LINE",
+            &record.location.line().to_string(),
+        );
+        if !self.identifier.is_empty() {
+            encode_field(&mut datagram, "SYSLOG_IDENTIFIER", &self.identifier);
+        }
+        for (key, value) in &record.fields {
+            if let Some(name) = field_name(key) {
+                encode_field(&mut datagram, &name, value);
+            }
+        }
+        datagram
+    }
+}
+
+impl Sink for JournaldSink {
+    fn log(&self, record: &Record, _formatted: &str) {
+        // A logger has nowhere to report its own failures, so failed sends are dropped.
+        if self.socket.send(&self.encode(record)).is_err() {
+            count_dropped(record.level, &record.target);
+        }
+    }
+}
+
+/// Write a single field, using the length prefixed form for values containing a line break.
+fn encode_field(datagram: &mut Vec<u8>, name: &str, value: &str) {
+    datagram.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        datagram.push(b'\n');
+        datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        datagram.push(b'=');
+    }
+    datagram.extend_from_slice(value.as_bytes());
+    datagram.push(b'\n');
+}
+
+/// Convert a field name into journald's form: uppercase ascii letters, digits and underscores,
+/// not starting with an underscore or digit, at most 64 characters, prefixed if journald gives
+/// the name a meaning. Returns None if nothing usable is left.
+fn field_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .skip_while(|c| *c == '_' || c.is_ascii_digit())
+        .take(MAX_FIELD_NAME_LEN)
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+    match RESERVED_FIELD_NAMES.contains(&name.as_str()) {
+        true => Some(format!("{RESERVED_FIELD_PREFIX}{name}")),
+        false => Some(name),
+    }
+}
+
+#[cfg(test)]
+mod journald_tests {
+    use super::*;
+    use crate::log::network::test_sockets::TIMEOUT;
+    use crate::log::{Log, LogLevel, static_level};
+    use crate::{env, process};
+
+    #[test]
+    fn should_convert_field_names() {
+        assert_eq!(field_name("requestId").as_deref(), Some("REQUESTID"));
+        assert_eq!(
+            field_name("http.status-code").as_deref(),
+            Some("HTTP_STATUS_CODE")
+        );
+        assert_eq!(field_name("_1private").as_deref(), Some("PRIVATE"));
+        assert_eq!(field_name("__"), None);
+        assert_eq!(field_name("message").as_deref(), Some("FIELDS_MESSAGE"));
+        assert_eq!(field_name("code.file").as_deref(), Some("FIELDS_CODE_FILE"));
+        assert_eq!(field_name("messages").as_deref(), Some("MESSAGES"));
+    }
+
+    #[test]
+    fn should_encode_multi_line_values_with_length_prefix() {
+        let mut datagram = Vec::new();
+        encode_field(&mut datagram, "SINGLE", "value");
+        encode_field(&mut datagram, "MULTI", "a\nb");
+        let mut expected = b"SINGLE=value\nMULTI\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(datagram, expected);
+    }
+
+    #[test]
+    fn should_send_records_to_socket() {
+        if !static_level::enabled(LogLevel::Warn) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_journald_{}.sock", process::id()));
+        let _ = crate::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        server.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let sink = JournaldSink::with_path(&path)
+            .unwrap()
+            .set_identifier("app");
+        let log = Log::new_ex()
+            .set_log_level(LogLevel::Trace)
+            .set_sink(sink)
+            .done()
+            .child_with_field("requestId", "abc");
+        let line = line!() + 1;
+        log.warnf("message", &vec![1]);
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let _ = crate::fs::remove_file(&path);
+
+        let message = "message\n[\n    1,\n]";
+        let mut expected = b"PRIORITY=4\nMESSAGE\n".to_vec();
+        expected.extend_from_slice(&(message.len() as u64).to_le_bytes());
+        expected.extend_from_slice(message.as_bytes());
+        expected.extend_from_slice(
+            format!(
+                "\nCODE_FILE={}\nCODE_LINE={line}\nSYSLOG_IDENTIFIER=app\nREQUESTID=abc\n",
+                file!()
+            )
+            .as_bytes(),
+        );
+        assert_eq!(&buf[..len], expected.as_slice());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::env;
+use crate::log::Format;
+use crate::sync::RwLock;
+
+const AWS_LAMBDA_LOG_FORMAT_ENV_VAR_NAME: &str = "AWS_LAMBDA_LOG_FORMAT";
+pub(crate) const AWS_LAMBDA_LOG_LEVEL_ENV_VAR_NAME: &str = "AWS_LAMBDA_LOG_LEVEL";
+pub(crate) const REQUEST_ID_FIELD: &str = "requestId";
+
+static REQUEST_ID: RwLock<Option<String>> = RwLock::new(None);
+
+/// Set the aws lambda request id of the current invocation.
+/// It is added to every log, from every logger, as the `requestId` field until cleared or
+/// replaced, so call this at the start of each invocation.
+/// To set it for a single logger instead, use Log::child_with_request_id.
+pub fn set_request_id<T: Into<String>>(request_id: T) {
+    if let Ok(mut current) = REQUEST_ID.write() {
+        *current = Some(request_id.into());
+    }
+}
+
+/// Clear the aws lambda request id set by set_request_id.
+pub fn clear_request_id() {
+    if let Ok(mut current) = REQUEST_ID.write() {
+        *current = None;
+    }
+}
+
+/// The request id of the current invocation, if set.
+pub(crate) fn request_id() -> Option<String> {
+    REQUEST_ID.read().ok().and_then(|current| current.clone())
+}
+
+/// The format aws lambda's advanced logging controls ask for, if any.
+pub(crate) fn log_format() -> Option<Format> {
+    match env::var(AWS_LAMBDA_LOG_FORMAT_ENV_VAR_NAME) {
+        Ok(format) if format.eq_ignore_ascii_case("json") => Some(Format::Json),
+        Ok(format) if format.eq_ignore_ascii_case("text") => Some(Format::Text),
+        _ => None,
+    }
+}
@@ -0,0 +1,526 @@
+use crate::borrow::Cow;
+use crate::fmt::{self, Debug, Write};
+use crate::log::Record;
+use crate::term;
+
+/// Bytes of data kept past the byte limit while it is formatted, so that redaction, which runs
+/// before the limit is applied, still sees a secret which straddles the limit whole.
+const SLACK: usize = 1024;
+
+/// The marker ending text which was cut while it was formatted, when its full size isn't known.
+const TRUNCATED: &str = "…[truncated]";
+
+/// Size limits for each record, so logging a large value can't produce a multi-megabyte line.
+///
+/// Text over a byte limit is cut at the last whole character or ansi sequence that fits, and ends
+/// with a marker such as `…[truncated 1024 bytes]`. If the text kept has ansi codes, a reset is
+/// added before the marker so colour doesn't bleed into the rest of the output.
+///
+/// The item and depth limits apply to the pretty printed data of logf and logp, and to context.
+/// Collections with more items end with a line such as `... 95 more items`, and anything nested
+/// deeper is written as `{ ... }`. These limits are applied as the data is formatted, and
+/// formatting stops soon after the byte limit is passed, so a large value is never held in
+/// memory whole. Data cut that way ends with `…[truncated]`, as its full size isn't known.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::{Limits, Log};
+///
+/// let limits = Limits::new()
+///     .set_max_message_bytes(1024)
+///     .set_max_data_bytes(4096)
+///     .set_max_items(20)
+///     .set_max_depth(4);
+/// let log = Log::new_ex().set_limits(limits).done();
+/// log.infof("numbers", &(0..1000).collect::<Vec<_>>()); // the first 20, then `... 980 more items`
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    max_message_bytes: Option<usize>,
+    max_data_bytes: Option<usize>,
+    max_items: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+impl Limits {
+    /// No limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the most bytes of a message, and of each error in an error chain, kept.
+    /// Default: No limit.
+    pub fn set_max_message_bytes(mut self, max: usize) -> Self {
+        self.max_message_bytes = Some(max);
+        self
+    }
+
+    /// Set the most bytes of pretty printed data, context and backtraces kept.
+    /// Default: No limit.
+    pub fn set_max_data_bytes(mut self, max: usize) -> Self {
+        self.max_data_bytes = Some(max);
+        self
+    }
+
+    /// Set the most items written for each collection, struct or tuple in pretty printed data.
+    /// Default: No limit.
+    pub fn set_max_items(mut self, max: usize) -> Self {
+        self.max_items = Some(max);
+        self
+    }
+
+    /// Set how many levels of nesting are written in pretty printed data, 0 writing only the
+    /// outermost value's type, e.g. `Config { ... }`.
+    /// Default: No limit.
+    pub fn set_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Apply the limits to a record.
+    pub(crate) fn apply(&self, record: &mut Record) {
+        if let Some(max) = self.max_message_bytes
+            && let Cow::Owned(truncated) = truncate(&record.msg, max)
+        {
+            record.msg = truncated;
+        }
+        for error in record.errors.iter_mut() {
+            if let Some(max) = self.max_message_bytes
+                && let Cow::Owned(truncated) = truncate(error, max)
+            {
+                *error = truncated;
+            }
+        }
+        // the items and depth of data are limited as it is formatted
+        if let Some(data) = record.data.as_mut()
+            && let Some(max) = self.max_data_bytes
+            && let Cow::Owned(truncated) = truncate(data, max)
+        {
+            *data = truncated;
+        }
+        if let Some(backtrace) = record.backtrace.as_mut()
+            && let Some(max) = self.max_data_bytes
+            && let Cow::Owned(truncated) = truncate(backtrace, max)
+        {
+            *backtrace = truncated;
+        }
+        if let Cow::Owned(limited) = self.limit_data(&record.context) {
+            record.context = limited;
+        }
+    }
+
+    /// Pretty print the data of logf within the limits.
+    pub(crate) fn pretty_data(&self, data: &dyn Debug) -> String {
+        self.pretty(data, self.max_data_bytes)
+    }
+
+    /// Pretty print the data of logp, which is its message, within the limits.
+    pub(crate) fn pretty_message(&self, data: &dyn Debug) -> String {
+        self.pretty(data, self.max_message_bytes)
+    }
+
+    /// Pretty print data with the item and depth limits applied as it is formatted, stopping
+    /// once the text kept is over max bytes plus SLACK. The byte limit itself is applied to the
+    /// record later, after redaction.
+    fn pretty(&self, data: &dyn Debug, max_bytes: Option<usize>) -> String {
+        if max_bytes.is_none() && self.max_items.is_none() && self.max_depth.is_none() {
+            return fmt::pretty(data);
+        }
+
+        let cap = max_bytes.map_or(usize::MAX, |max| max.saturating_add(SLACK));
+        let mut pretty = Pretty::new(self, cap);
+        let stopped = fmt::write(&mut pretty, format_args!("{data:#?}")).is_err();
+        let mut output = pretty.finish();
+        if stopped {
+            output.push_str(TRUNCATED);
+        }
+        output
+    }
+
+    fn limit_data<'a>(&self, data: &'a str) -> Cow<'a, str> {
+        let data = match self.max_items.is_some() || self.max_depth.is_some() {
+            true => self.limit_pretty(data),
+            false => Cow::Borrowed(data),
+        };
+        match (self.max_data_bytes, data) {
+            (None, data) => data,
+            (Some(max), Cow::Borrowed(data)) => truncate(data, max),
+            (Some(max), Cow::Owned(data)) => Cow::Owned(truncate(&data, max).into_owned()),
+        }
+    }
+
+    /// Limit the items and depth of `{:#?}` output.
+    fn limit_pretty<'a>(&self, pretty: &'a str) -> Cow<'a, str> {
+        let mut limited = Pretty::new(self, usize::MAX);
+        let _ = limited.write_str(pretty);
+        let output = limited.finish();
+        match limited.limited {
+            true => Cow::Owned(output),
+            false => Cow::Borrowed(pretty),
+        }
+    }
+}
+
+/// Writes `{:#?}` output within the item and depth limits line by line, so only the text kept is
+/// held in memory. Writing fails once the text kept is over the cap.
+struct Pretty<'a> {
+    limits: &'a Limits,
+    cap: usize,
+    output: String,
+    /// The line being written, kept up to the cap.
+    line: String,
+    /// The last character of the line being written.
+    last: Option<char>,
+    /// The blocks the line being written is nested in, innermost last.
+    blocks: Vec<Block>,
+    /// A newline is due before the next line of output.
+    newline: bool,
+    /// Anything has been left out.
+    limited: bool,
+}
+
+/// A block opened by a line ending in `{`, `[` or `(`, up to the line closing it at the same
+/// indent.
+struct Block {
+    indent: usize,
+    items: usize,
+    /// The block is written, rather than left out with its parent's items.
+    shown: bool,
+    /// The block is past the depth limit, written as `{ ... }`.
+    collapsed: bool,
+}
+
+impl<'a> Pretty<'a> {
+    fn new(limits: &'a Limits, cap: usize) -> Self {
+        Self {
+            limits,
+            cap,
+            output: String::new(),
+            line: String::new(),
+            last: None,
+            blocks: Vec::new(),
+            newline: false,
+            limited: false,
+        }
+    }
+
+    fn extend_line(&mut self, s: &str) {
+        if let Some(last) = s.chars().next_back() {
+            self.last = Some(last);
+        }
+        let room = self.cap.saturating_sub(self.line.len());
+        let mut end = room.min(s.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.line.push_str(&s[..end]);
+    }
+
+    fn end_line(&mut self) {
+        let line = crate::mem::take(&mut self.line);
+        let opens = matches!(self.last.take(), Some('{' | '[' | '('));
+        let indent = indent(&line);
+        if let Some(block) = self.blocks.last()
+            && block.indent == indent
+            && line.trim_start().starts_with(['}', ']', ')'])
+        {
+            let block = self.blocks.pop().unwrap_or_else(|| unreachable!());
+            return self.close(&block, Some(&line));
+        }
+
+        let depth = self.blocks.len();
+        let max_items = self.limits.max_items.unwrap_or(usize::MAX);
+        let shown = match self.blocks.last_mut() {
+            None => true,
+            Some(parent) => {
+                parent.items += 1;
+                parent.shown && !parent.collapsed && parent.items <= max_items
+            }
+        };
+        if shown {
+            self.push_line(&line);
+        }
+        if opens {
+            let collapsed = shown && self.limits.max_depth.is_some_and(|max| depth >= max);
+            if collapsed {
+                self.newline = false;
+            }
+            self.blocks.push(Block {
+                indent,
+                items: 0,
+                shown,
+                collapsed,
+            });
+        }
+    }
+
+    /// Close a block, with the line closing it if there is one.
+    fn close(&mut self, block: &Block, line: Option<&str>) {
+        if block.collapsed {
+            self.output.push_str(" ... ");
+            self.output
+                .push_str(line.map(str::trim_start).unwrap_or_default());
+            self.newline = true;
+            self.limited = true;
+            return;
+        }
+        if !block.shown {
+            return;
+        }
+        if let Some(more) = self
+            .limits
+            .max_items
+            .and_then(|max| block.items.checked_sub(max))
+            && more > 0
+        {
+            self.push_line(&format!(
+                "{:indent$}{}",
+                "",
+                more_items(more),
+                indent = block.indent + 4
+            ));
+            self.limited = true;
+        }
+        if let Some(line) = line {
+            self.push_line(line);
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        if self.newline {
+            self.output.push('\n');
+        }
+        self.output.push_str(line);
+        self.newline = true;
+    }
+
+    /// Take the text kept, ending the last line and any blocks which are never closed unless
+    /// writing stopped at the cap.
+    fn finish(&mut self) -> String {
+        if self.output.len() <= self.cap {
+            if !self.line.is_empty() {
+                self.end_line();
+            }
+            while let Some(block) = self.blocks.pop() {
+                self.close(&block, None);
+            }
+        }
+        crate::mem::take(&mut self.output)
+    }
+}
+
+impl fmt::Write for Pretty<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.output.len() > self.cap {
+            return Err(fmt::Error);
+        }
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.extend_line(first);
+        }
+        for line in lines {
+            self.end_line();
+            if self.output.len() > self.cap {
+                return Err(fmt::Error);
+            }
+            self.extend_line(line);
+        }
+        match self.output.len() > self.cap {
+            true => Err(fmt::Error),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The line written in place of the items left out of a collection.
+fn more_items(count: usize) -> String {
+    match count {
+        1 => String::from("... 1 more item"),
+        count => format!("... {count} more items"),
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Cut text to at most max bytes, without splitting a character or ansi sequence, followed by a
+/// truncation marker. Text already cut while it was formatted keeps its marker without a size.
+pub(crate) fn truncate(s: &str, max: usize) -> Cow<'_, str> {
+    if s.len() <= max {
+        return Cow::Borrowed(s);
+    }
+
+    let mut end = 0;
+    let mut has_ansi = false;
+    while end < s.len() {
+        let len = match term::sequence_len(&s[end..]) {
+            Some(len) => {
+                has_ansi = true;
+                len
+            }
+            None => s[end..].chars().next().map_or(1, char::len_utf8),
+        };
+        if end + len > max {
+            break;
+        }
+        end += len;
+    }
+
+    let reset = match has_ansi && s[..end].contains('\x1b') {
+        true => term::RESET,
+        false => "",
+    };
+    let marker = match s.ends_with(TRUNCATED) {
+        true => String::from(TRUNCATED),
+        false => format!("…[truncated {} bytes]", s.len() - end),
+    };
+    Cow::Owned(format!("{}{reset}{marker}", &s[..end]))
+}
+
+#[cfg(test)]
+mod limits_tests {
+    use super::*;
+    use crate::fmt;
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Config {
+        name: &'static str,
+        servers: Vec<u16>,
+        nested: Inner,
+    }
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Inner {
+        value: Option<u8>,
+    }
+
+    fn config() -> String {
+        fmt::pretty(&Config {
+            name: "app",
+            servers: vec![1, 2, 3],
+            nested: Inner { value: Some(1) },
+        })
+    }
+
+    #[test]
+    fn should_leave_text_within_limit() {
+        assert!(matches!(truncate("short", 5), Cow::Borrowed("short")));
+    }
+
+    #[test]
+    fn should_truncate_on_character_boundary() {
+        assert_eq!(truncate("abcdef", 3), "abc…[truncated 3 bytes]");
+        assert_eq!(truncate("aé", 2), "a…[truncated 2 bytes]");
+        assert_eq!(truncate("日本語", 7), "日本…[truncated 3 bytes]");
+    }
+
+    #[test]
+    fn should_not_split_ansi_sequences() {
+        let painted = format!("\x1b[31mred{}", term::RESET);
+        assert_eq!(
+            truncate(&painted, 6),
+            format!("\x1b[31mr{}…[truncated 6 bytes]", term::RESET)
+        );
+        assert_eq!(truncate(&painted, 3), "…[truncated 12 bytes]");
+    }
+
+    #[test]
+    fn should_limit_items() {
+        let limited = Limits::new()
+            .set_max_items(2)
+            .limit_pretty(&config())
+            .into_owned();
+        assert_eq!(
+            limited,
+            "Config {\n    name: \"app\",\n    servers: [\n        1,\n        2,\n        ... 1 more item\n    ],\n    ... 1 more item\n}"
+        );
+    }
+
+    #[test]
+    fn should_limit_depth() {
+        let limits = Limits::new().set_max_depth(1);
+        assert_eq!(
+            limits.limit_pretty(&config()),
+            "Config {\n    name: \"app\",\n    servers: [ ... ],\n    nested: Inner { ... },\n}"
+        );
+        assert_eq!(
+            Limits::new().set_max_depth(0).limit_pretty(&config()),
+            "Config { ... }"
+        );
+        assert!(matches!(
+            Limits::new().set_max_depth(3).limit_pretty(&config()),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn should_apply_byte_limit_after_items() {
+        let data = fmt::pretty(&(0..1000).collect::<Vec<_>>());
+        let limited = Limits::new()
+            .set_max_items(3)
+            .set_max_data_bytes(1000)
+            .limit_data(&data);
+        assert_eq!(
+            limited,
+            "[\n    0,\n    1,\n    2,\n    ... 997 more items\n]"
+        );
+        let truncated = Limits::new().set_max_data_bytes(10).limit_data(&data);
+        assert!(truncated.starts_with("[\n    0,\n …[truncated "));
+    }
+
+    #[test]
+    fn should_limit_items_and_depth_while_formatting() {
+        let limits = Limits::new().set_max_items(3).set_max_data_bytes(1000);
+        assert_eq!(
+            limits.pretty_data(&(0..1000).collect::<Vec<_>>()),
+            "[\n    0,\n    1,\n    2,\n    ... 997 more items\n]"
+        );
+        let nested = Config {
+            name: "app",
+            servers: vec![1, 2, 3],
+            nested: Inner { value: Some(1) },
+        };
+        assert_eq!(
+            Limits::new().set_max_depth(1).pretty_data(&nested),
+            "Config {\n    name: \"app\",\n    servers: [ ... ],\n    nested: Inner { ... },\n}"
+        );
+        assert_eq!(Limits::new().pretty_data(&nested), fmt::pretty(&nested));
+    }
+
+    /// Writes a million lines, counting how many were written before formatting stopped.
+    struct Endless(crate::cell::Cell<usize>);
+
+    impl Debug for Endless {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for _ in 0..1_000_000 {
+                f.write_str("a line of data\n")?;
+                self.0.set(self.0.get() + 1);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_stop_formatting_past_the_byte_limit() {
+        let endless = Endless(crate::cell::Cell::new(0));
+        let limits = Limits::new().set_max_data_bytes(100);
+        let pretty = limits.pretty_data(&endless);
+        assert!(endless.0.get() < 100, "{} lines written", endless.0.get());
+        assert!(pretty.len() <= 100 + SLACK + 15 + TRUNCATED.len());
+        assert!(pretty.ends_with(TRUNCATED));
+
+        let mut record = Record {
+            data: Some(pretty),
+            ..Record::test(crate::log::LogLevel::Info, "message")
+        };
+        limits.apply(&mut record);
+        let data = record.data.unwrap_or_default();
+        assert!(data.starts_with("a line of data\na line"));
+        assert_eq!(data.len(), 100 + TRUNCATED.len());
+        assert!(data.ends_with(TRUNCATED));
+    }
+}
@@ -1,9 +1,30 @@
-use crate::log::LogLevel;
-use crate::term;
-use crate::{chrono, env, fmt, fmt::Display, str::FromStr};
+use crate::cell::OnceCell;
+use crate::log::duplicates::{Duplicates, Repeat};
+use crate::log::env_config;
+use crate::log::error_chain;
+use crate::log::lambda::{self, AWS_LAMBDA_LOG_LEVEL_ENV_VAR_NAME, REQUEST_ID_FIELD};
+use crate::log::metrics::{self, Counters, Outcome};
+use crate::log::rate_limit::{self, RateLimiter};
+use crate::log::sampling::Sampler;
+use crate::log::static_level;
+use crate::log::{
+    EnvConfigError, Format, LevelDirectives, Limits, LogLevel, LogWriter, Multiline, RateLimit,
+    Record, Redaction, Sampling, Sink, Template, format,
+};
+use crate::panic::Location;
+use crate::sync::Arc;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::term::{self, ColourPolicy, Stream};
+use crate::{backtrace::Backtrace, error::Error};
+use crate::{borrow::Cow, chrono, env, fmt, fmt::Display, process, str::FromStr};
 
-const AWS_LAMBDA_ENV_VAR_NAME: &str = "AWS_LAMBDA_FUNCTION_NAME";
-const LOG_LEVEL_ENV_VAR_NAME: &str = "LOG_LEVEL";
+/// The fields added by add_process_fields.
+const SERVICE_FIELD: &str = "service";
+const HOST_FIELD: &str = "host";
+const PID_FIELD: &str = "pid";
+const VERSION_FIELD: &str = "version";
+/// The field holding how many records a rate limit dropped before the one it is on.
+const SUPPRESSED_FIELD: &str = "suppressed";
 
 #[derive(Clone)]
 pub enum Timestamp {
@@ -12,28 +33,140 @@ pub enum Timestamp {
     DateAndTime,
 }
 
-impl ToString for Timestamp {
-    fn to_string(&self) -> String {
+impl Timestamp {
+    /// Format a time according to these timestamp settings, None if timestamps are disabled.
+    pub(crate) fn format(&self, time: &chrono::DateTime<chrono::Local>) -> Option<String> {
         match self {
-            Timestamp::None => String::from(""),
-            Timestamp::Time => chrono::Local::now().format("[%H:%M:%S%.3f] ").to_string(),
-            Timestamp::DateAndTime => chrono::Local::now()
-                .format("[%Y-%m-%d %H:%M:%S%.3f] ")
-                .to_string(),
+            Timestamp::None => None,
+            Timestamp::Time => Some(time.format("%H:%M:%S%.3f").to_string()),
+            Timestamp::DateAndTime => Some(time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()),
         }
     }
 }
 
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format(&chrono::Local::now()) {
+            Some(timestamp) => write!(f, "[{timestamp}] "),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether colour is written to each output, resolved from the colour policy when it is set.
+#[derive(Clone, Copy)]
+struct Colours {
+    policy: ColourPolicy,
+    stdout: bool,
+    stderr: bool,
+}
+
+impl Colours {
+    fn resolve(policy: ColourPolicy) -> Self {
+        Self {
+            policy,
+            stdout: policy.should_colour(Stream::Stdout),
+            stderr: policy.should_colour(Stream::Stderr),
+        }
+    }
+
+    /// should output written to the stream be coloured, None being output which isn't a standard
+    /// stream, e.g. a file or socket
+    fn colour(&self, stream: Option<Stream>) -> bool {
+        match stream {
+            Some(Stream::Stdout) => self.stdout,
+            Some(Stream::Stderr) => self.stderr,
+            None => self.policy == ColourPolicy::Always,
+        }
+    }
+}
+
+/// The levels set by environment variables, read once when a logger is created and shared by all
+/// of its children.
+struct EnvLevels {
+    directives: Option<LevelDirectives>,
+    lambda: Option<LogLevel>,
+    /// LOG_LEVEL, when it is set but invalid.
+    error: Option<EnvConfigError>,
+}
+
+/// Has an invalid LOG_LEVEL been reported, by a logger or init_from_env.
+static ENV_ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Stop loggers warning about an invalid LOG_LEVEL, as the error has been returned already.
+pub(crate) fn mark_env_error_reported() {
+    ENV_ERROR_REPORTED.store(true, Ordering::Relaxed);
+}
+
+impl EnvLevels {
+    fn read(var: impl Fn(&str) -> Option<String>) -> Self {
+        let (directives, error) = match env_config::level_directives(&var) {
+            Ok(directives) => (directives, None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            directives,
+            lambda: var(AWS_LAMBDA_LOG_LEVEL_ENV_VAR_NAME)
+                .and_then(|level| LogLevel::from_str(&level).ok()),
+            error,
+        }
+    }
+}
+
+/// A record and, once needed, the record as formatted by the logger.
+struct Rendered<'a> {
+    log: &'a Log,
+    record: Record,
+    colour: bool,
+    formatted: OnceCell<String>,
+}
+
+impl Rendered<'_> {
+    fn formatted(&self) -> &str {
+        self.formatted
+            .get_or_init(|| self.log.format_log(&self.record, self.colour))
+    }
+}
+
 #[derive(Clone)]
 pub struct Log {
+    backtraces: bool,
+    colours: Colours,
     context: String,
+    counters: Option<Arc<Counters>>,
+    duplicates: Option<Arc<Duplicates>>,
+    env_levels: Arc<EnvLevels>,
+    fields: Vec<(String, String)>,
+    format: Format,
+    level_directives: LevelDirectives,
+    limits: Limits,
     log_level: LogLevel,
-    logging_function: fn(String) -> (),
+    logging_function: Option<fn(String) -> ()>,
+    multiline: Multiline,
     prefix: String,
-    suppress_ansi: bool,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    redaction: Option<Redaction>,
+    sampler: Option<Arc<Sampler>>,
+    sinks: Vec<Arc<dyn Sink>>,
+    target: String,
+    target_metrics: bool,
+    template: Template,
     timestamp: Timestamp,
 }
 
+impl Drop for Log {
+    /// write the summary of any run of duplicates which hasn't ended, once no other logger can
+    /// end it
+    fn drop(&mut self) {
+        if let Some(duplicates) = self.duplicates.take().and_then(Arc::into_inner)
+            && let Some(summary) = duplicates.finish()
+        {
+            self.write(summary.record, summary.enabled);
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct LogrsBuilder {
     instance: Log,
 }
@@ -41,43 +174,73 @@ pub struct LogrsBuilder {
 impl Default for Log {
     fn default() -> Self {
         Self {
+            backtraces: false,
+            colours: Colours::resolve(ColourPolicy::Auto),
             context: String::from(""),
+            counters: None,
+            duplicates: None,
+            env_levels: Arc::new(EnvLevels::read(|key| env::var(key).ok())),
+            fields: Vec::new(),
+            format: lambda::log_format().unwrap_or_default(),
+            level_directives: LevelDirectives::default(),
+            limits: Limits::default(),
             log_level: LogLevel::Info,
-            logging_function: |string: String| eprintln!("{string}"),
+            logging_function: Some(|string: String| eprintln!("{string}")),
+            multiline: Multiline::Preserve,
             prefix: String::from(""),
-            suppress_ansi: Self::should_suppress_ansi(),
+            rate_limiter: None,
+            redaction: None,
+            sampler: None,
+            sinks: Vec::new(),
+            target: String::new(),
+            target_metrics: false,
+            template: Template::default(),
             timestamp: Timestamp::None,
         }
     }
 }
 
-impl Default for LogrsBuilder {
-    fn default() -> Self {
-        Self {
-            instance: Log::default(),
-        }
-    }
-}
-
 macro_rules! log_methods_at_level {
-    ($log_name: ident, $logf_name:ident, $logp_name:ident, $level: ident) => {
+    ($log_name: ident, $logf_name:ident, $logp_name:ident, $log_once_name:ident, $level: ident) => {
         /// Log a message
-        pub fn $log_name<T: AsRef<str>>(&self, msg: T)
+        #[track_caller]
+        pub fn $log_name<T>(&self, msg: T)
         where
-            T: crate::fmt::Display,
+            T: AsRef<str> + crate::fmt::Display,
         {
-            self.logger(LogLevel::$level, msg);
+            if const { static_level::enabled(LogLevel::$level) } {
+                self.logger(LogLevel::$level, &msg, None);
+            }
         }
         /// Log a message with data
-        pub fn $logf_name<T: AsRef<str>>(&self, msg: T, data: &dyn crate::fmt::Debug)
+        #[track_caller]
+        pub fn $logf_name<T>(&self, msg: T, data: &dyn crate::fmt::Debug)
         where
-            T: crate::fmt::Display,
+            T: AsRef<str> + crate::fmt::Display,
         {
-            self.$log_name(format!("{}\n{:#?}", msg, data));
+            if const { static_level::enabled(LogLevel::$level) } {
+                self.logger(LogLevel::$level, &msg, Some(data));
+            }
         }
         /// Log data using crate_ex::fmt::pretty
+        #[track_caller]
         pub fn $logp_name(&self, data: &dyn crate::fmt::Debug) {
-            self.$log_name(crate::fmt::pretty(data));
+            if const { static_level::enabled(LogLevel::$level) } {
+                self.logger(LogLevel::$level, &self.limits.pretty_message(data), None);
+            }
+        }
+        /// Log a message the first time this call site is reached with the level enabled only
+        #[track_caller]
+        pub fn $log_once_name<T>(&self, msg: T)
+        where
+            T: AsRef<str> + crate::fmt::Display,
+        {
+            if const { static_level::enabled(LogLevel::$level) }
+                && self.is_wanted(LogLevel::$level)
+                && rate_limit::first_at(Location::caller())
+            {
+                self.logger(LogLevel::$level, &msg, None);
+            }
         }
     };
 }
@@ -108,7 +271,7 @@ impl Log {
     /// A child logger is a new instance which inherits from it's parent, with the option of adding
     /// a prefix, and new context to each log
     pub fn child_with_new_context(&self, prefix: Option<&str>, context: &dyn fmt::Debug) -> Self {
-        let context = format!("\nContext:\n{:#?}", context);
+        let context = fmt::pretty(context);
         let mut child = self.child(prefix);
         child.context = context;
         child
@@ -121,90 +284,518 @@ impl Log {
         prefix: Option<&str>,
         context: &dyn fmt::Debug,
     ) -> Self {
-        let context = fmt::format(format_args!("{}\n{:#?}", self.context, context));
+        let context = match self.context.is_empty() {
+            true => fmt::pretty(context),
+            false => format!("{}\n{:#?}", self.context, context),
+        };
         let mut child = self.child(prefix);
         child.context = context;
         child
     }
 
-    log_methods_at_level!(trace, tracef, tracep, Trace);
-    log_methods_at_level!(debug, debugf, debugp, Debug);
-    log_methods_at_level!(info, infof, infop, Info);
-    log_methods_at_level!(warn, warnf, warnp, Warn);
-    log_methods_at_level!(error, errorf, errorp, Error);
-    log_methods_at_level!(fatal, fatalf, fatalp, Fatal);
+    /// A child logger is a new instance which inherits from it's parent, with an additional field
+    /// added to each log. Fields are written as `key=value` in text, and as keys in structured
+    /// formats. A field with the same key as one on the parent replaces it.
+    pub fn child_with_field<T: Display>(&self, key: &str, value: T) -> Self {
+        let mut child = self.clone();
+        child.fields.retain(|(existing, _)| existing != key);
+        child.fields.push((key.to_string(), value.to_string()));
+        child
+    }
+
+    /// A named logger is a child whose name is appended to it's parent's target, e.g.
+    /// `log.named("db").named("pool")` has the target `app.db.pool` when the root is named `app`.
+    /// The target leads the prefix in text, is the `target` key in structured formats, and selects
+    /// the level from any level directives. Names containing dots add one segment per part.
+    pub fn named(&self, name: &str) -> Self {
+        let mut child = self.clone();
+        for segment in segments(name) {
+            if !child.target.is_empty() {
+                child.target.push('.');
+            }
+            child.target.push_str(&segment);
+        }
+        child.attach_counters();
+        child
+    }
+
+    /// The dotted target of this logger, empty if it has no name.
+    pub fn target(&self) -> String {
+        self.target.clone()
+    }
+
+    /// A child logger is a new instance which inherits from it's parent, with the aws lambda
+    /// request id added to each log as the `requestId` field.
+    /// Create one per invocation, or use log::set_request_id to set it for every logger.
+    pub fn child_with_request_id(&self, request_id: &str) -> Self {
+        self.child_with_field(REQUEST_ID_FIELD, request_id)
+    }
+
+    /// An io::Write which logs each line written to it at the given level.
+    /// See LogWriter.
+    #[track_caller]
+    pub fn writer(&self, level: LogLevel) -> LogWriter {
+        LogWriter::new(self.clone(), level, Location::caller())
+    }
+
+    log_methods_at_level!(trace, tracef, tracep, trace_once, Trace);
+    log_methods_at_level!(debug, debugf, debugp, debug_once, Debug);
+    log_methods_at_level!(info, infof, infop, info_once, Info);
+    log_methods_at_level!(warn, warnf, warnp, warn_once, Warn);
+    log_methods_at_level!(error, errorf, errorp, error_once, Error);
+    log_methods_at_level!(fatal, fatalf, fatalp, fatal_once, Fatal);
+
+    /// Log an expression and its pretty printed value at debug level, used by log_dbg!
+    #[track_caller]
+    pub fn debug_expr(&self, expr: &str, value: &dyn fmt::Debug) {
+        if const { static_level::enabled(LogLevel::Debug) } {
+            let location = Location::caller();
+            let msg = format!(
+                "[{}:{}:{}] {expr}",
+                location.file(),
+                location.line(),
+                location.column()
+            );
+            self.logger_at(LogLevel::Debug, &msg, Some(value), location);
+        }
+    }
 
-    /// Logs formatted log to self.logging_function
-    fn logger<T: AsRef<str>>(&self, level: LogLevel, msg: T)
+    /// Log a message with an error and every error in its source chain, at error level
+    #[track_caller]
+    pub fn error_chain<T>(&self, msg: T, error: &dyn Error)
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
-        if !self.should_log(level) {
-            return;
+        if const { static_level::enabled(LogLevel::Error) } {
+            self.logger_with_error(LogLevel::Error, &msg, None, Some(error), Location::caller());
         }
-
-        let log = self.format_log(level, msg);
-        (self.logging_function)(log)
     }
 
-    /// format all strings into single string
-    fn format_log<T: AsRef<str>>(&self, log_level: LogLevel, msg: T) -> String
+    /// Log a message at a level chosen at runtime
+    #[track_caller]
+    pub fn log<T>(&self, level: LogLevel, msg: T)
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
-        let timestamp = self.timestamp.to_string();
-        let colour = match self.suppress_ansi {
-            true => String::from(""),
-            false => term::Colour::from(log_level).to_string(),
+        self.logger(level, &msg, None);
+    }
+
+    /// Logs formatted log from the caller's location
+    #[track_caller]
+    fn logger(&self, level: LogLevel, msg: &dyn Display, data: Option<&dyn fmt::Debug>) {
+        self.logger_at(level, msg, data, Location::caller());
+    }
+
+    /// Logs formatted log to any sinks, then self.logging_function
+    pub(crate) fn logger_at(
+        &self,
+        level: LogLevel,
+        msg: &dyn Display,
+        data: Option<&dyn fmt::Debug>,
+        location: &'static Location<'static>,
+    ) {
+        self.logger_with_error(level, msg, data, None, location);
+    }
+
+    /// Logs formatted log, with the chain of any error, to any sinks, then self.logging_function
+    fn logger_with_error(
+        &self,
+        level: LogLevel,
+        msg: &dyn Display,
+        data: Option<&dyn fmt::Debug>,
+        error: Option<&dyn Error>,
+        location: &'static Location<'static>,
+    ) {
+        if !static_level::enabled(level) {
+            return;
+        }
+        self.report_env_error(location);
+
+        let enabled = self.should_log(level);
+        if !enabled {
+            metrics::count(self.counters.as_deref(), Outcome::Suppressed, level);
+            if !self.any_sink_wants(level) {
+                return;
+            }
+        }
+
+        if let Some(sampler) = &self.sampler
+            && !sampler.keep(level)
+        {
+            return self.count_dropped_by_filter(enabled, level);
+        }
+
+        let mut text = None;
+        let mut suppressed = 0;
+        if let Some(rate_limiter) = &self.rate_limiter {
+            let key = match rate_limiter.keys_by_message() {
+                true => text.insert(msg.to_string()).as_str(),
+                false => "",
+            };
+            match rate_limiter.check(location, level, key) {
+                None => return self.count_dropped_by_filter(enabled, level),
+                Some(count) => suppressed = count,
+            }
+        }
+
+        let text = text.unwrap_or_else(|| msg.to_string());
+        let mut record = self.record(
+            level,
+            location,
+            text,
+            data.map(|data| self.limits.pretty_data(data)),
+        );
+        if let Some(error) = error {
+            record.errors = error_chain::chain(error);
+            if self.backtraces {
+                record.backtrace = Some(Backtrace::force_capture().to_string());
+            }
+        }
+        if suppressed > 0 {
+            record
+                .fields
+                .push((SUPPRESSED_FIELD.to_string(), suppressed.to_string()));
+        }
+        if let Some(duplicates) = &self.duplicates {
+            match duplicates.check(&record, enabled) {
+                Repeat::New => {}
+                Repeat::Duplicate => return self.count_dropped_by_filter(enabled, level),
+                Repeat::Ended(summary) => self.write(summary.record, summary.enabled),
+            }
+        }
+        self.write(record, enabled);
+    }
+
+    /// a new record from this logger
+    fn record(
+        &self,
+        level: LogLevel,
+        location: &'static Location<'static>,
+        msg: String,
+        data: Option<String>,
+    ) -> Record {
+        Record {
+            level,
+            time: chrono::Local::now(),
+            location,
+            target: self.target(),
+            prefix: self.prefix.clone(),
+            msg,
+            data,
+            errors: Vec::new(),
+            backtrace: None,
+            context: self.context.clone(),
+            fields: self.record_fields(),
+        }
+    }
+
+    /// write a record to the sinks which want it, then the logging function if the level is
+    /// enabled. Outputs which aren't coloured get the record with any caller ansi codes removed.
+    fn write(&self, mut record: Record, enabled: bool) {
+        if let Some(redaction) = &self.redaction {
+            redaction.apply(&mut record);
+        }
+        self.limits.apply(&mut record);
+        let level = record.level;
+        let function_colour = self.colour(Some(Stream::Stderr));
+        let any_colour = (enabled && self.logging_function.is_some() && function_colour)
+            || self.sinks.iter().any(|sink| {
+                (enabled || Self::sink_wants(sink, level)) && self.colour(sink.stream())
+            });
+        let coloured = any_colour.then(|| self.rendered(record.clone(), true));
+        Self::strip_ansi(&mut record);
+        let plain = self.rendered(record, false);
+        let output = |colour: bool| match &coloured {
+            Some(coloured) if colour => coloured,
+            _ => &plain,
         };
-        let level = log_level.to_string().to_uppercase();
-        let colour_off = match self.suppress_ansi {
-            true => String::from(""),
-            false => term::Colour::Off.to_string(),
+
+        for sink in &self.sinks {
+            if enabled || Self::sink_wants(sink, level) {
+                let output = output(self.colour(sink.stream()));
+                match enabled {
+                    true => sink.log(&output.record, output.formatted()),
+                    false => sink.log_suppressed(&output.record, output.formatted()),
+                }
+            }
+        }
+        if !enabled {
+            return;
+        }
+        if let Some(logging_function) = self.logging_function {
+            logging_function(output(function_colour).formatted().to_string())
+        }
+        metrics::count(self.counters.as_deref(), Outcome::Logged, level);
+    }
+
+    fn rendered(&self, record: Record, colour: bool) -> Rendered<'_> {
+        Rendered {
+            log: self,
+            record,
+            colour,
+            formatted: OnceCell::new(),
+        }
+    }
+
+    /// should output written to the stream be coloured, structured formats never are
+    fn colour(&self, stream: Option<Stream>) -> bool {
+        !self.format.is_structured() && self.colours.colour(stream)
+    }
+
+    /// count a record dropped by sampling, rate limiting or duplicate collapsing as suppressed,
+    /// unless it was already counted as below the enabled level
+    fn count_dropped_by_filter(&self, enabled: bool, level: LogLevel) {
+        if enabled {
+            metrics::count(self.counters.as_deref(), Outcome::Suppressed, level);
+        }
+    }
+
+    /// does the sink want records at the level, regardless of the logger's level
+    fn sink_wants(sink: &Arc<dyn Sink>, level: LogLevel) -> bool {
+        sink.level().is_some_and(|sink_level| level >= sink_level)
+    }
+
+    fn any_sink_wants(&self, level: LogLevel) -> bool {
+        self.sinks.iter().any(|sink| Self::sink_wants(sink, level))
+    }
+
+    /// would a record at the level be written anywhere, by the logging function or a sink
+    fn is_wanted(&self, level: LogLevel) -> bool {
+        self.should_log(level) || self.any_sink_wants(level)
+    }
+
+    /// use the shared counters for this logger's target, if target metrics are enabled
+    fn attach_counters(&mut self) {
+        self.counters = match self.target_metrics && !self.target.is_empty() {
+            true => Some(metrics::target_counters(&self.target)),
+            false => None,
         };
-        let prefix = &self.prefix;
-        let context = &self.context;
+    }
 
-        format!("{timestamp}{colour}{level}{colour_off}:{prefix} {msg} {context}")
+    /// format record into single string using the configured format
+    fn format_log(&self, record: &Record, colour: bool) -> String {
+        match self.format {
+            Format::Text => {
+                let log = self.template.render(record, &self.timestamp, colour);
+                match self.multiline.apply(&log) {
+                    Cow::Borrowed(_) => log,
+                    Cow::Owned(log) => log,
+                }
+            }
+            Format::Json => format::json(record),
+            Format::Logfmt => format::logfmt(record),
+        }
     }
 
-    /// should the logger log based on currently set internal log level, and environment variable
-    /// LOG_LEVEL
-    fn should_log(&self, log_level: LogLevel) -> bool {
-        let level = match env::var(LOG_LEVEL_ENV_VAR_NAME) {
-            Ok(level) => match LogLevel::from_str(level.as_str()) {
-                Ok(level) => level,
-                Err(_) => self.log_level,
-            },
-            Err(_) => self.log_level,
+    /// fields for a new record, including the current aws lambda request id if one is set
+    fn record_fields(&self) -> Vec<(String, String)> {
+        let mut fields = self.fields.clone();
+        if !fields.iter().any(|(key, _)| key == REQUEST_ID_FIELD)
+            && let Some(request_id) = lambda::request_id()
+        {
+            fields.push((REQUEST_ID_FIELD.to_string(), request_id));
+        }
+        fields
+    }
+
+    /// remove any ansi codes passed in by the caller from every part of the record
+    fn strip_ansi(record: &mut Record) {
+        let strip = |s: &mut String| {
+            if let Cow::Owned(stripped) = term::strip_ansi(s) {
+                *s = stripped;
+            }
         };
+        strip(&mut record.prefix);
+        strip(&mut record.msg);
+        strip(&mut record.context);
+        record.errors.iter_mut().for_each(strip);
+        if let Some(data) = record.data.as_mut() {
+            strip(data);
+        }
+    }
+
+    /// should the logger log based on currently set internal log level, and environment variables
+    /// LOG_LEVEL, then AWS_LAMBDA_LOG_LEVEL
+    fn should_log(&self, log_level: LogLevel) -> bool {
+        log_level >= self.enabled_level()
+    }
 
-        log_level >= level
+    /// the lowest level logged for this logger's target, from the first of
+    /// - LOG_LEVEL, as level directives, e.g. `info,app.db=debug`
+    /// - AWS_LAMBDA_LOG_LEVEL
+    /// - the level directives set on the builder
+    /// - the log level set on the builder
+    ///
+    /// The environment variables are read when the logger is created.
+    fn enabled_level(&self) -> LogLevel {
+        let env_levels = &self.env_levels;
+        env_levels
+            .directives
+            .as_ref()
+            .and_then(|directives| directives.level_for(&self.target))
+            .or(env_levels.lambda)
+            .or_else(|| self.level_directives.level_for(&self.target))
+            .unwrap_or(self.log_level)
+    }
+
+    /// The logger as if no level environment variables were set, for tests which depend on its
+    /// own level while others set LOG_LEVEL.
+    #[cfg(test)]
+    pub(crate) fn without_env_levels(self) -> Self {
+        self.with_env_levels(&[])
+    }
+
+    /// The logger as if it was created with the environment variables.
+    #[cfg(test)]
+    pub(crate) fn with_env_levels(mut self, vars: &[(&str, &str)]) -> Self {
+        self.env_levels = Arc::new(EnvLevels::read(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        }));
+        self
     }
 
-    /// should ansi codes be suppressed
-    /// for example when used inside aws lambda
-    fn should_suppress_ansi() -> bool {
-        // Suppress ansi codes when run inside aws lambda
-        env::var_exists(AWS_LAMBDA_ENV_VAR_NAME)
+    /// write an invalid LOG_LEVEL as a warning, once per process, the first time a logger logging
+    /// warnings logs
+    fn report_env_error(&self, location: &'static Location<'static>) {
+        if let Some(error) = &self.env_levels.error
+            && static_level::enabled(LogLevel::Warn)
+            && self.should_log(LogLevel::Warn)
+            && !ENV_ERROR_REPORTED.swap(true, Ordering::Relaxed)
+        {
+            let msg = format!("{error}, it is ignored");
+            let record = self.record(LogLevel::Warn, location, msg, None);
+            self.write(record, true);
+        }
     }
 }
 
 impl LogrsBuilder {
     /// Disables internal use of ansi codes.
-    /// For now, any ansi codes passed into the logging functions will be untouched.
+    /// Any ansi codes passed into the logging functions are stripped from the output.
     pub fn disable_ansi(&mut self) -> &mut Self {
-        self.instance.suppress_ansi = true;
+        self.set_colour_policy(ColourPolicy::Never)
+    }
+
+    /// Set when internal ansi codes are used.
+    /// ColourPolicy::Auto is resolved, against the environment and whether stdout and stderr are
+    /// terminals, when this is called. The logging function is resolved against stderr, and each
+    /// sink against Sink::stream, so a sink writing to a file or socket is never coloured.
+    /// When an output isn't coloured, any ansi codes passed into the logging functions are
+    /// stripped from it too.
+    /// Default: ColourPolicy::Auto.
+    pub fn set_colour_policy(&mut self, colour_policy: ColourPolicy) -> &mut Self {
+        self.instance.colours = Colours::resolve(colour_policy);
         self
     }
 
     /// Set default enabled log level to log at.
+    /// LOG_LEVEL and AWS_LAMBDA_LOG_LEVEL take precedence, read when the builder is created. An
+    /// invalid LOG_LEVEL is ignored, and written as a warning by the first log.
     /// Default: LogLevel::Info.
     pub fn set_log_level(&mut self, log_level: LogLevel) -> &mut Self {
         self.instance.log_level = log_level;
         self
     }
 
+    /// Set the name of the root logger, which leads the target of every named child.
+    /// Default: No name, so the target of `log.named("db")` is `db`.
+    pub fn set_name(&mut self, name: &str) -> &mut Self {
+        self.instance.target = segments(name).collect::<Vec<_>>().join(".");
+        self
+    }
+
+    /// Count records for each target as well as globally, see log::target_metrics.
+    /// Counters are shared by every named logger with the same target.
+    pub fn enable_target_metrics(&mut self) -> &mut Self {
+        self.instance.target_metrics = true;
+        self
+    }
+
+    /// Replace secrets with `***` in every part of each record, before it is formatted or sent
+    /// to any sink. See Redaction.
+    /// Default: Nothing is redacted, other than Redacted values.
+    pub fn set_redaction(&mut self, redaction: Redaction) -> &mut Self {
+        self.instance.redaction = Some(redaction);
+        self
+    }
+
+    /// Set size limits for the message, data and context of each record, applied after any
+    /// redaction. See Limits.
+    /// Default: No limits.
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.instance.limits = limits;
+        self
+    }
+
+    /// Limit how often records are logged, per call site or per message.
+    /// Records over the limit are dropped, and counted as suppressed by log::metrics.
+    /// See RateLimit.
+    /// Default: No limit.
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) -> &mut Self {
+        self.instance.rate_limiter = Some(Arc::new(RateLimiter::new(rate_limit)));
+        self
+    }
+
+    /// Keep only a sample of the records at a level, e.g. Sampling::Probability(0.01) for 1%.
+    /// Records left out are dropped before they are formatted, and counted as suppressed by
+    /// log::metrics.
+    /// Default: Every record is kept.
+    pub fn set_sampling(&mut self, log_level: LogLevel, sampling: Sampling) -> &mut Self {
+        let sampler = match &self.instance.sampler {
+            Some(sampler) => sampler.set_sampling(log_level, sampling),
+            None => Sampler::new().set_sampling(log_level, sampling),
+        };
+        self.instance.sampler = Some(Arc::new(sampler));
+        self
+    }
+
+    /// Seed the random numbers used by Sampling::Probability, so the same records are kept on
+    /// every run, e.g. in tests.
+    /// Default: Seeded from the clock.
+    pub fn set_sampling_seed(&mut self, seed: u64) -> &mut Self {
+        let sampler = match &self.instance.sampler {
+            Some(sampler) => sampler.set_seed(seed),
+            None => Sampler::new().set_seed(seed),
+        };
+        self.instance.sampler = Some(Arc::new(sampler));
+        self
+    }
+
+    /// Capture a backtrace with each error_chain log, written after the error chain.
+    /// Default: Backtraces are not captured.
+    pub fn enable_backtraces(&mut self) -> &mut Self {
+        self.instance.backtraces = true;
+        self
+    }
+
+    /// Collapse consecutive identical records, with the same level, target, prefix, message,
+    /// data, errors, context and fields, into the first, followed by `last message repeated N
+    /// times` once a different record is logged, or the last logger sharing them is dropped.
+    /// Default: Every record is written.
+    pub fn collapse_duplicates(&mut self) -> &mut Self {
+        self.instance.duplicates = Some(Arc::new(Duplicates::new()));
+        self
+    }
+
+    /// Set the level for a target and all of it's descendants, overriding the default log level.
+    /// The most specific matching target wins, e.g. `app.db.pool` uses the level set for `app.db`
+    /// unless one is set for `app.db.pool` itself.
+    pub fn set_target_level(&mut self, target: &str, log_level: LogLevel) -> &mut Self {
+        self.instance
+            .level_directives
+            .set_target_level(target, log_level);
+        self
+    }
+
+    /// Set the level directives, replacing any target levels already set.
+    /// A default level in the directives overrides the default log level.
+    pub fn set_level_directives(&mut self, level_directives: LevelDirectives) -> &mut Self {
+        self.instance.level_directives = level_directives;
+        self
+    }
+
     /// Set timestamp configuration for log message.
     /// Timestamp::None -> No timestamp on log messages.
     /// Timestamp::Time -> Time is prefixed to log messages in the format [HH:MM:SS.ms].
@@ -216,22 +807,110 @@ impl LogrsBuilder {
         self
     }
 
+    /// Set the output format.
+    /// Format::Text -> Each log is laid out by the template.
+    /// Format::Json -> Each log is a single line JSON object, with any ansi codes stripped.
+    /// Format::Logfmt -> Each log is a single line of `key=value` pairs, with any ansi codes
+    /// stripped.
+    /// Default: Format::Json when AWS_LAMBDA_LOG_FORMAT=JSON, otherwise Format::Text.
+    pub fn set_format(&mut self, format: Format) -> &mut Self {
+        self.instance.format = format;
+        self
+    }
+
+    /// Set how line breaks in text logs are written.
+    /// Multiline::Preserve -> Line breaks are written as is.
+    /// Multiline::Escape -> Line breaks are escaped, so each log is a single line.
+    /// Multiline::Indent(marker) -> Continuation lines are prefixed with the marker.
+    /// Default: Multiline::Preserve.
+    pub fn set_multiline(&mut self, multiline: Multiline) -> &mut Self {
+        self.instance.multiline = multiline;
+        self
+    }
+
+    /// Set the template used to lay out each log message.
+    /// See Template for the placeholders and syntax available.
+    /// Default: DEFAULT_TEMPLATE.
+    pub fn set_template(&mut self, template: Template) -> &mut Self {
+        self.instance.template = template;
+        self
+    }
+
     /// Set the logging function for logrs.
+    /// ColourPolicy::Auto colours its output when stderr is a terminal, as it would for the
+    /// default.
     /// Default: Logs to stderr using eprintln.
     pub fn set_logging_function(&mut self, f: fn(String) -> ()) -> &mut Self {
-        self.instance.logging_function = f;
+        self.instance.logging_function = Some(f);
+        self
+    }
+
+    /// Add a sink which receives every log, alongside the logging function and any other sinks.
+    /// Pass an Arc to keep a handle to the sink.
+    pub fn add_sink<S: Sink + 'static>(&mut self, sink: S) -> &mut Self {
+        self.instance.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Send every log to the sink only, replacing the logging function and any other sinks.
+    pub fn set_sink<S: Sink + 'static>(&mut self, sink: S) -> &mut Self {
+        self.instance.logging_function = None;
+        self.instance.sinks = vec![Arc::new(sink)];
         self
     }
 
+    /// Add a field to each log of the instance, and all of it's children.
+    pub fn add_field<T: Display>(&mut self, key: &str, value: T) -> &mut Self {
+        self.instance = self.instance.child_with_field(key, value);
+        self
+    }
+
+    /// Add `service`, `host`, `pid` and `version` fields to each log of the instance, and all of
+    /// it's children. The host is found with env::hostname, and left out if it can't be, and the
+    /// pid is std::process::id. Calling this again replaces the fields.
+    /// e.g. `add_process_fields("billing", env!("CARGO_PKG_VERSION"))`
+    pub fn add_process_fields(&mut self, service: &str, version: &str) -> &mut Self {
+        self.add_field(SERVICE_FIELD, service);
+        if let Some(host) = env::hostname() {
+            self.add_field(HOST_FIELD, host);
+        }
+        self.add_field(PID_FIELD, process::id())
+            .add_field(VERSION_FIELD, version)
+    }
+
+    /// Configure from environment variables, leaving the settings of any variable not set.
+    /// LOG_LEVEL -> Level directives, e.g. `info` or `warn,app.db=debug`.
+    /// LOG_FORMAT -> `text`, `json` or `logfmt`.
+    /// LOG_TIMESTAMP -> `none`, `time` or `datetime`. Only used by the text format, as json and
+    /// logfmt always write the date and time.
+    /// LOG_COLOR -> `auto`, `always` or `never`. Defaults to `never` when LOG_FILE is set.
+    /// LOG_FILE -> A file every log is appended to, instead of stderr.
+    /// Returns an error listing every invalid variable, in which case nothing is changed.
+    pub fn configure_from_env(&mut self) -> Result<&mut Self, EnvConfigError> {
+        env_config::configure(self, |key| env::var(key).ok())?;
+        Ok(self)
+    }
+
     /// Done building and return logrs instance.
     pub fn done(&self) -> Log {
-        self.instance.clone()
+        let mut log = self.instance.clone();
+        log.attach_counters();
+        log
     }
 }
 
+/// the non-empty, dot separated segments of a logger name
+fn segments(name: &str) -> impl Iterator<Item = String> {
+    name.split('.')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(String::from)
+}
+
 #[cfg(test)]
 mod should_log_tests {
     use super::*;
+    use crate::log::env_config::LOG_LEVEL_ENV_VAR_NAME;
     // use crate::Logrs;
     // use crate::log_level::LogLevel;
     // use crate::logrs::LOG_LEVEL_ENV_VAR_NAME;
@@ -250,3 +929,585 @@ mod should_log_tests {
         assert!(Log::new().should_log(LogLevel::Trace))
     }
 }
+
+#[cfg(test)]
+mod named_tests {
+    use super::*;
+    use crate::log::env_config::LOG_LEVEL_ENV_VAR_NAME;
+
+    #[test]
+    fn should_build_dotted_target_from_names() {
+        let log = Log::new_ex().set_name("app").done();
+        assert_eq!(log.named("db").named("pool").target(), "app.db.pool");
+        assert_eq!(log.named("db.pool").target(), "app.db.pool");
+        assert_eq!(Log::new().named("db").target(), "db");
+        assert_eq!(Log::new().named("").target(), "");
+    }
+
+    #[test]
+    fn should_use_most_specific_target_level() {
+        let log = Log::new_ex()
+            .set_name("app")
+            .set_log_level(LogLevel::Warn)
+            .set_target_level("app.db", LogLevel::Debug)
+            .set_target_level("app.db.pool", LogLevel::Error)
+            .done();
+        let log = log.without_env_levels();
+        assert_eq!(log.enabled_level(), LogLevel::Warn);
+        assert_eq!(log.named("db").enabled_level(), LogLevel::Debug);
+        assert_eq!(
+            log.named("db").named("query").enabled_level(),
+            LogLevel::Debug
+        );
+        assert_eq!(
+            log.named("db").named("pool").enabled_level(),
+            LogLevel::Error
+        );
+    }
+
+    #[test]
+    fn should_prefer_level_directives_from_env() {
+        let log = Log::new_ex()
+            .set_name("app")
+            .set_target_level("app.db", LogLevel::Error)
+            .done()
+            .named("db");
+        let env_log = log
+            .clone()
+            .with_env_levels(&[(LOG_LEVEL_ENV_VAR_NAME, "warn,app.db=trace")]);
+        assert_eq!(env_log.enabled_level(), LogLevel::Trace);
+        assert_eq!(env_log.named("other").enabled_level(), LogLevel::Trace);
+        let env_log = log
+            .clone()
+            .with_env_levels(&[(LOG_LEVEL_ENV_VAR_NAME, "app.http=trace")]);
+        assert_eq!(env_log.enabled_level(), LogLevel::Error);
+    }
+}
+
+#[cfg(test)]
+mod format_log_tests {
+    use super::*;
+    use crate::io;
+    use crate::log::env_config::LOG_LEVEL_ENV_VAR_NAME;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static OUTPUT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn capture(log: String) {
+        OUTPUT.with(|output| output.borrow_mut().push(log));
+    }
+
+    fn take_output() -> Vec<String> {
+        OUTPUT.with(|output| output.take())
+    }
+
+    #[test]
+    fn should_strip_caller_ansi_codes_when_ansi_disabled() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(capture)
+            .done()
+            .child(Some(&term::Colour::Green.paint("child:")));
+        log.error(term::Colour::Red.paint("painted"));
+        assert_eq!(take_output(), vec!["ERROR: child: painted"]);
+    }
+
+    #[test]
+    fn should_strip_caller_ansi_codes_from_json() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .set_colour_policy(ColourPolicy::Always)
+            .set_format(Format::Json)
+            .set_logging_function(capture)
+            .done();
+        log.error(term::Colour::Red.paint("painted"));
+        assert!(take_output()[0].contains(r#""message":"painted""#));
+    }
+
+    #[test]
+    fn should_write_request_id_field() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(capture)
+            .done();
+        log.child_with_request_id("abc-123").error("text");
+        log.child_with_request_id("abc-123")
+            .child_with_new_context(None, &1)
+            .child_with_field("attempt", 2)
+            .error("text");
+        assert_eq!(
+            take_output(),
+            vec![
+                "ERROR: text requestId=abc-123",
+                "ERROR: text requestId=abc-123 attempt=2\nContext:\n1"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_write_process_fields_in_every_child() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .add_process_fields("billing", "1.2.3")
+            .set_logging_function(capture)
+            .done();
+        log.child(Some("child:")).named("db").error("text");
+        let output = take_output();
+        assert!(output[0].starts_with("ERROR: db: child: text service=billing "));
+        assert!(output[0].ends_with(&format!(" pid={} version=1.2.3", process::id())));
+
+        let log = Log::new_ex()
+            .set_format(Format::Json)
+            .add_process_fields("billing", "1.2.3")
+            .add_process_fields("billing", "1.2.4")
+            .set_logging_function(capture)
+            .done();
+        log.error("text");
+        let output = take_output();
+        assert!(output[0].contains(r#""service":"billing""#));
+        assert!(output[0].ends_with(&format!(r#""pid":"{}","version":"1.2.4"}}"#, process::id())));
+        assert!(!output[0].contains("1.2.3"));
+    }
+
+    #[test]
+    fn should_write_target_before_prefix() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_name("app")
+            .set_logging_function(capture)
+            .done()
+            .named("db")
+            .child(Some("child:"))
+            .named("pool");
+        log.error("text");
+        assert_eq!(take_output(), vec!["ERROR: app.db.pool: child: text"]);
+    }
+
+    #[test]
+    fn should_count_logged_records_per_target() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .set_name("format_log_tests")
+            .enable_target_metrics()
+            .set_logging_function(capture)
+            .done()
+            .named("counted");
+        log.error("logged");
+        log.error("logged");
+        take_output();
+
+        let snapshot = metrics::target_metrics("format_log_tests.counted").unwrap();
+        assert_eq!(snapshot.logged.get(LogLevel::Error), 2);
+        assert_eq!(snapshot.logged.total(), 2);
+        assert!(metrics::metrics().logged.get(LogLevel::Error) >= 2);
+    }
+
+    #[test]
+    fn should_rate_limit_and_count_suppressed_records() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_rate_limit(RateLimit::new(1, crate::time::Duration::from_secs(3600)))
+            .set_logging_function(capture)
+            .done();
+        for _ in 0..3 {
+            log.error("retrying");
+        }
+        log.error("different call site");
+        assert_eq!(
+            take_output(),
+            vec!["ERROR: retrying", "ERROR: different call site"]
+        );
+    }
+
+    #[test]
+    fn should_not_format_rate_limited_records() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        use crate::sync::atomic::AtomicUsize;
+        static FORMATTED: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl fmt::Debug for Counted {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                FORMATTED.fetch_add(1, Ordering::Relaxed);
+                f.write_str("Counted")
+            }
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_rate_limit(RateLimit::new(1, crate::time::Duration::from_secs(3600)))
+            .set_logging_function(capture)
+            .done();
+        for _ in 0..3 {
+            log.errorf("retrying", &Counted);
+        }
+        assert_eq!(FORMATTED.load(Ordering::Relaxed), 1);
+        assert_eq!(take_output(), vec!["ERROR: retrying\nCounted"]);
+    }
+
+    #[test]
+    fn should_collapse_duplicates() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .collapse_duplicates()
+            .set_logging_function(capture)
+            .done();
+        for _ in 0..5000 {
+            log.error("retrying");
+        }
+        log.error("gave up");
+        assert_eq!(
+            take_output(),
+            vec![
+                "ERROR: retrying",
+                "ERROR: last message repeated 4999 times",
+                "ERROR: gave up"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_collapse_duplicates_per_logger_and_write_pending_summary_on_drop() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .collapse_duplicates()
+            .set_logging_function(capture)
+            .done();
+        let child = log.child(Some("child"));
+        log.error("retrying");
+        child.error("retrying");
+        child.error("retrying");
+        drop(log);
+        assert_eq!(
+            take_output(),
+            vec!["ERROR: retrying", "ERROR: child retrying"]
+        );
+        drop(child);
+        assert_eq!(
+            take_output(),
+            vec!["ERROR: child last message repeated 1 time"]
+        );
+    }
+
+    #[test]
+    fn should_not_mark_call_site_once_when_level_is_disabled() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_log_level(LogLevel::Fatal)
+            .set_logging_function(capture)
+            .done()
+            .without_env_levels();
+        let enabled = Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(capture)
+            .done();
+        for log in [&log, &enabled] {
+            log.error_once("once");
+        }
+        assert_eq!(take_output(), vec!["ERROR: once"]);
+    }
+
+    #[test]
+    fn should_compile_out_levels_below_static_max_level() {
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_log_level(LogLevel::Trace)
+            .set_logging_function(capture)
+            .done();
+        log.trace("trace");
+        log.debugf("debug", &1);
+        log.infop(&"info");
+        log.warn_once("warn");
+        log.log(LogLevel::Error, "error");
+        log.fatal("fatal");
+        let expected: Vec<&str> = [
+            (LogLevel::Trace, "TRACE: trace"),
+            (LogLevel::Debug, "DEBUG: debug\n1"),
+            (LogLevel::Info, "INFO: \"info\""),
+            (LogLevel::Warn, "WARN: warn"),
+            (LogLevel::Error, "ERROR: error"),
+            (LogLevel::Fatal, "FATAL: fatal"),
+        ]
+        .into_iter()
+        .filter(|(level, _)| static_level::enabled(*level))
+        .map(|(_, log)| log)
+        .collect();
+        assert_eq!(take_output(), expected);
+    }
+
+    #[test]
+    fn should_sample_records() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_sampling(LogLevel::Error, Sampling::EveryNth(2))
+            .set_logging_function(capture)
+            .done();
+        for attempt in 0..4 {
+            log.error(format!("attempt {attempt}"));
+        }
+        log.fatal("fatal");
+        assert_eq!(
+            take_output(),
+            vec!["ERROR: attempt 0", "ERROR: attempt 2", "FATAL: fatal"]
+        );
+    }
+
+    #[test]
+    fn should_log_once_per_call_site() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(capture)
+            .done();
+        for attempt in 0..3 {
+            log.error_once(format!("attempt {attempt}"));
+        }
+        assert_eq!(take_output(), vec!["ERROR: attempt 0"]);
+    }
+
+    #[test]
+    fn should_redact_every_part_of_the_record() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Login {
+            user: &'static str,
+            password: &'static str,
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_format(Format::Json)
+            .set_redaction(Redaction::default().add_pattern(r"Bearer \S+".parse().unwrap()))
+            .set_logging_function(capture)
+            .done()
+            .child_with_new_context(None, &vec!["Bearer abc"])
+            .child_with_field("token", "abc");
+        log.errorf(
+            "sent Bearer abc",
+            &Login {
+                user: "admin",
+                password: "hunter2",
+            },
+        );
+        let output = take_output();
+        assert!(!output[0].contains("abc") && !output[0].contains("hunter2"));
+        assert!(output[0].contains(r#""message":"sent ***""#));
+        assert!(output[0].contains(r#"password: ***,"#));
+        assert!(output[0].contains(r#""token":"***""#));
+    }
+
+    #[test]
+    fn should_log_expressions_and_return_their_values() {
+        if !static_level::enabled(LogLevel::Debug) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_log_level(LogLevel::Debug)
+            .set_logging_function(capture)
+            .done();
+        let a = 2;
+        let (line, value) = (line!(), crate::log_dbg!(log; a * 2));
+        assert_eq!(value, 4);
+        let (b, c) = crate::log_dbg!(&log; a + 1, String::from("moved"),);
+        assert_eq!((b, c.as_str()), (3, "moved"));
+        crate::log_dbg!(log;);
+
+        let output = take_output();
+        assert_eq!(output.len(), 4);
+        assert!(output[0].starts_with(&format!("DEBUG: [{}:{line}:", file!())));
+        assert!(output[0].ends_with("] a * 2\n4"));
+        assert!(output[1].ends_with("] a + 1\n3"));
+        assert!(output[2].ends_with("] String::from(\"moved\")\n\"moved\""));
+        assert!(output[3].starts_with(&format!("DEBUG: [{}:", file!())));
+    }
+
+    #[test]
+    fn should_log_error_chain() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(capture)
+            .done();
+        #[derive(Debug)]
+        struct ConfigError(io::Error);
+        impl Display for ConfigError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("reading config")
+            }
+        }
+        impl Error for ConfigError {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let error = ConfigError(io::Error::other("permission denied"));
+        log.error_chain("failed to start", &error);
+        assert_eq!(
+            take_output(),
+            vec!["ERROR: failed to start\nerror: reading config\n  caused by: permission denied"]
+        );
+
+        let log = Log::new_ex()
+            .set_format(Format::Json)
+            .enable_backtraces()
+            .set_logging_function(capture)
+            .done();
+        log.error_chain("failed to start", &error);
+        let output = take_output();
+        assert!(output[0].contains(
+            r#""message":"failed to start","errors":["reading config","permission denied"],"backtrace":""#
+        ));
+    }
+
+    #[test]
+    fn should_limit_message_and_data() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_limits(Limits::new().set_max_message_bytes(4).set_max_items(1))
+            .set_logging_function(capture)
+            .done();
+        log.errorf("message", &vec![1, 2, 3]);
+        assert_eq!(
+            take_output(),
+            vec!["ERROR: mess…[truncated 3 bytes]\n[\n    1,\n    ... 2 more items\n]"]
+        );
+    }
+
+    #[test]
+    fn should_escape_data_and_context_onto_a_single_line() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_multiline(Multiline::Escape)
+            .set_logging_function(capture)
+            .done()
+            .child_with_new_context(None, &vec![1]);
+        log.errorf("message", &"data\nwith line break");
+        assert_eq!(
+            take_output(),
+            vec![r#"ERROR: message\n"data\\nwith line break"\nContext:\n[\n    1,\n]"#]
+        );
+    }
+
+    #[test]
+    fn should_report_invalid_level_env_once_per_process_when_warnings_are_logged() {
+        if !static_level::enabled(LogLevel::Warn) {
+            return;
+        }
+        let with_level = |log_level| {
+            let log = Log::new_ex()
+                .disable_ansi()
+                .set_log_level(log_level)
+                .set_logging_function(capture)
+                .done();
+            log.with_env_levels(&[(LOG_LEVEL_ENV_VAR_NAME, "loud")])
+        };
+        with_level(LogLevel::Error).error("quiet");
+        let log = with_level(LogLevel::Warn);
+        log.error("first");
+        log.child(None).error("second");
+        with_level(LogLevel::Warn).error("other root");
+        assert_eq!(
+            take_output(),
+            vec![
+                "ERROR: quiet",
+                "WARN: invalid log environment: LOG_LEVEL=\"loud\" is an invalid log level, \
+                 expected one of trace, debug, info, warn, error, fatal or silent, it is ignored",
+                "ERROR: first",
+                "ERROR: second",
+                "ERROR: other root"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_resolve_auto_colour_per_output() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        struct Plain;
+        impl Sink for Plain {
+            fn log(&self, _record: &Record, formatted: &str) {
+                capture(format!("sink {formatted}"));
+            }
+        }
+        let mut log = Log::new_ex()
+            .set_logging_function(capture)
+            .add_sink(Plain)
+            .done();
+        log.colours = Colours {
+            policy: ColourPolicy::Auto,
+            stdout: false,
+            stderr: true,
+        };
+        log.error(term::Colour::Red.paint("painted"));
+        let output = take_output();
+        assert_eq!(output[0], "sink ERROR: painted");
+        assert!(output[1].ends_with(&term::Colour::Red.paint("painted")));
+
+        log.colours = Colours::resolve(ColourPolicy::Always);
+        log.error("text");
+        let output = take_output();
+        assert!(output.iter().all(|log| log.contains("\x1b[")));
+    }
+
+    #[test]
+    fn should_keep_caller_ansi_codes_when_ansi_enabled() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let log = Log::new_ex()
+            .set_colour_policy(ColourPolicy::Always)
+            .set_logging_function(capture)
+            .done();
+        log.error(term::Colour::Red.paint("painted"));
+        let output = take_output();
+        assert!(output[0].ends_with(&term::Colour::Red.paint("painted")));
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::LazyLock};
 
-use crate::{fmt::Display, str::FromStr, term::Colour};
+use crate::{error::Error, fmt, fmt::Display, str::FromStr, term::Colour};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Hash)]
 pub enum LogLevel {
@@ -13,7 +13,7 @@ pub enum LogLevel {
     Silent,
 }
 
-const DEFAULT_COLOURS: LazyLock<HashMap<LogLevel, Colour>> = LazyLock::new(|| {
+static DEFAULT_COLOURS: LazyLock<HashMap<LogLevel, Colour>> = LazyLock::new(|| {
     let mut hashmap = HashMap::new();
 
     hashmap.insert(LogLevel::Trace, Colour::Grey);
@@ -26,8 +26,20 @@ const DEFAULT_COLOURS: LazyLock<HashMap<LogLevel, Colour>> = LazyLock::new(|| {
     hashmap
 });
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ParseLogLevelError;
 
+impl Display for ParseLogLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid log level, expected one of trace, debug, info, warn, error, fatal or silent"
+        )
+    }
+}
+
+impl Error for ParseLogLevelError {}
+
 impl FromStr for LogLevel {
     type Err = ParseLogLevelError;
 
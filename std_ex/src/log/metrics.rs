@@ -0,0 +1,208 @@
+use crate::collections::HashMap;
+use crate::log::LogLevel;
+use crate::sync::atomic::{AtomicU64, Ordering};
+use crate::sync::{Arc, LazyLock, Mutex, MutexGuard};
+
+/// Trace through to Silent.
+const LEVELS: usize = 7;
+
+/// Counters for every logger in the process.
+static GLOBAL: Counters = Counters::new();
+
+/// Counters for named loggers built with LogrsBuilder::enable_target_metrics, keyed by target.
+static TARGETS: LazyLock<Mutex<HashMap<String, Arc<Counters>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Number of records at each level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelCounts {
+    counts: [u64; LEVELS],
+}
+
+impl LevelCounts {
+    /// Number of records at the level.
+    pub fn get(&self, level: LogLevel) -> u64 {
+        self.counts[index(level)]
+    }
+
+    /// Number of records at every level.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// A snapshot of the log counters, taken with log::metrics or log::target_metrics.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::{self, LogLevel};
+///
+/// log::error("something went wrong");
+/// let metrics = log::metrics();
+/// println!("{} errors", metrics.logged.get(LogLevel::Error));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogMetrics {
+    /// Records written to the logging function and sinks.
+    pub logged: LevelCounts,
+    /// Records below the enabled level for their logger.
+    pub suppressed: LevelCounts,
+    /// Records a sink failed to deliver, as reported by log::count_dropped.
+    pub dropped: LevelCounts,
+}
+
+/// What happened to a record.
+#[derive(Clone, Copy)]
+pub(crate) enum Outcome {
+    Logged,
+    Suppressed,
+    Dropped,
+}
+
+/// Atomic per level counters for each outcome.
+pub(crate) struct Counters {
+    logged: [AtomicU64; LEVELS],
+    suppressed: [AtomicU64; LEVELS],
+    dropped: [AtomicU64; LEVELS],
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            logged: [const { AtomicU64::new(0) }; LEVELS],
+            suppressed: [const { AtomicU64::new(0) }; LEVELS],
+            dropped: [const { AtomicU64::new(0) }; LEVELS],
+        }
+    }
+
+    fn outcome(&self, outcome: Outcome) -> &[AtomicU64; LEVELS] {
+        match outcome {
+            Outcome::Logged => &self.logged,
+            Outcome::Suppressed => &self.suppressed,
+            Outcome::Dropped => &self.dropped,
+        }
+    }
+
+    fn count(&self, outcome: Outcome, level: LogLevel) {
+        self.outcome(outcome)[index(level)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LogMetrics {
+        let counts = |outcome| LevelCounts {
+            counts: self
+                .outcome(outcome)
+                .each_ref()
+                .map(|count| count.load(Ordering::Relaxed)),
+        };
+        LogMetrics {
+            logged: counts(Outcome::Logged),
+            suppressed: counts(Outcome::Suppressed),
+            dropped: counts(Outcome::Dropped),
+        }
+    }
+
+    fn reset(&self) {
+        for outcome in [Outcome::Logged, Outcome::Suppressed, Outcome::Dropped] {
+            for count in self.outcome(outcome) {
+                count.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Counts of records from every logger since the process started, or metrics were last reset.
+pub fn metrics() -> LogMetrics {
+    GLOBAL.snapshot()
+}
+
+/// Counts of records from named loggers with the target, None if target metrics were not
+/// enabled for it. See LogrsBuilder::enable_target_metrics.
+pub fn target_metrics(target: &str) -> Option<LogMetrics> {
+    targets().get(target).map(|counters| counters.snapshot())
+}
+
+/// Set every counter back to zero, including those of each target.
+pub fn reset_metrics() {
+    GLOBAL.reset();
+    for counters in targets().values() {
+        counters.reset();
+    }
+}
+
+/// Count a record a sink failed to deliver.
+/// Sinks should call this whenever they drop a record, so it shows up in LogMetrics::dropped.
+pub fn count_dropped(level: LogLevel, target: &str) {
+    GLOBAL.count(Outcome::Dropped, level);
+    if let Some(counters) = targets().get(target) {
+        counters.count(Outcome::Dropped, level);
+    }
+}
+
+/// Count a record globally, and for its target if it has counters.
+pub(crate) fn count(counters: Option<&Counters>, outcome: Outcome, level: LogLevel) {
+    GLOBAL.count(outcome, level);
+    if let Some(counters) = counters {
+        counters.count(outcome, level);
+    }
+}
+
+/// The counters for a target, created the first time they are asked for.
+pub(crate) fn target_counters(target: &str) -> Arc<Counters> {
+    Arc::clone(
+        targets()
+            .entry(target.to_string())
+            .or_insert_with(|| Arc::new(Counters::new())),
+    )
+}
+
+fn targets() -> MutexGuard<'static, HashMap<String, Arc<Counters>>> {
+    TARGETS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn index(level: LogLevel) -> usize {
+    level as usize - 1
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+
+    #[test]
+    fn should_count_each_outcome_per_level() {
+        let counters = Counters::new();
+        counters.count(Outcome::Logged, LogLevel::Warn);
+        counters.count(Outcome::Logged, LogLevel::Warn);
+        counters.count(Outcome::Logged, LogLevel::Error);
+        counters.count(Outcome::Suppressed, LogLevel::Trace);
+        counters.count(Outcome::Dropped, LogLevel::Fatal);
+
+        let metrics = counters.snapshot();
+        assert_eq!(metrics.logged.get(LogLevel::Warn), 2);
+        assert_eq!(metrics.logged.get(LogLevel::Info), 0);
+        assert_eq!(metrics.logged.total(), 3);
+        assert_eq!(metrics.suppressed.get(LogLevel::Trace), 1);
+        assert_eq!(metrics.dropped.get(LogLevel::Fatal), 1);
+
+        counters.reset();
+        assert_eq!(counters.snapshot(), LogMetrics::default());
+    }
+
+    #[test]
+    fn should_share_counters_per_target() {
+        let target = "metrics_tests.shared";
+        let first = target_counters(target);
+        let second = target_counters(target);
+        first.count(Outcome::Logged, LogLevel::Info);
+        count(Some(&second), Outcome::Logged, LogLevel::Info);
+        count_dropped(LogLevel::Info, target);
+
+        let snapshot = target_metrics(target).unwrap();
+        assert_eq!(snapshot.logged.get(LogLevel::Info), 2);
+        assert_eq!(snapshot.dropped.get(LogLevel::Info), 1);
+        assert!(metrics().dropped.get(LogLevel::Info) >= 1);
+        assert_eq!(target_metrics("metrics_tests.missing"), None);
+    }
+}
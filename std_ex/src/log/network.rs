@@ -0,0 +1,792 @@
+use crate::collections::VecDeque;
+use crate::io::{self, Write};
+use crate::log::{LogLevel, Record, Sink, count_dropped, format, syslog, syslog_severity};
+use crate::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use crate::sync::atomic::{AtomicU64, Ordering};
+use crate::sync::{Arc, Condvar, Mutex, MutexGuard};
+use crate::thread::{self, JoinHandle};
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::{env, fmt::Write as _};
+
+/// Largest GELF UDP datagram, including the 12 byte chunk header.
+const GELF_CHUNK_SIZE: usize = 8192;
+const GELF_CHUNK_HEADER_SIZE: usize = 12;
+const GELF_MAX_CHUNKS: usize = 128;
+const GELF_CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+/// Largest payload of a UDP datagram over IPv4.
+const UDP_MAX_PAYLOAD: usize = 65_507;
+
+/// The transport used by a NetworkSink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// How records are serialised by a NetworkSink.
+/// Framing::Gelf -> GELF 1.1 messages, null terminated over TCP and chunked over UDP, as read
+/// by Graylog.
+/// Framing::JsonLines -> The same JSON as Format::Json, newline terminated over TCP and one per
+/// datagram over UDP, as read by Logstash's json_lines codec.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    #[default]
+    Gelf,
+    JsonLines,
+}
+
+/// A sink which ships records to a log collector over TCP or UDP.
+///
+/// Records are serialised on the logging thread and queued for a background thread which sends
+/// them, so logging never blocks on the network. While the collector can't be reached the queue
+/// buffers records, dropping the oldest when full, and the connection is retried with
+/// exponential backoff. Records which can never be sent, such as a GELF message too large to
+/// chunk, are dropped rather than retried. The number of dropped records is reported by
+/// NetworkSink::dropped, and counted by log::metrics.
+///
+/// Dropping the sink sends whatever is still queued, unless the collector can't be reached within
+/// the timeout.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use std_ex::log::{Framing, Log, NetworkSink};
+///
+/// let sink = Arc::new(
+///     NetworkSink::tcp("graylog.internal:12201")
+///         .unwrap()
+///         .set_framing(Framing::Gelf)
+///         .done()
+///         .unwrap(),
+/// );
+/// let log = Log::new_ex().add_sink(Arc::clone(&sink)).done();
+/// log.info("shipped");
+/// println!("dropped {} records", sink.dropped());
+/// ```
+pub struct NetworkSink {
+    shared: Arc<Shared>,
+    protocol: Protocol,
+    framing: Framing,
+    host: String,
+    worker: Option<JoinHandle<()>>,
+}
+
+pub struct NetworkSinkBuilder {
+    addrs: Vec<SocketAddr>,
+    protocol: Protocol,
+    framing: Framing,
+    capacity: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    timeout: Duration,
+    host: String,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+    dropped: AtomicU64,
+    capacity: usize,
+}
+
+struct State {
+    queue: VecDeque<Queued>,
+    next_id: u64,
+    closed: bool,
+}
+
+/// A serialised record, numbered so the sender can tell if the one it sent was dropped.
+/// Over TCP the payload includes the delimiter.
+struct Queued {
+    id: u64,
+    level: LogLevel,
+    target: String,
+    payload: Vec<u8>,
+}
+
+impl Queued {
+    fn drop_record(self, shared: &Shared) {
+        shared.dropped.fetch_add(1, Ordering::Relaxed);
+        count_dropped(self.level, &self.target);
+    }
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+}
+
+/// How much of a record the current TCP connection has taken, when a write timed out part way.
+struct Partial {
+    id: u64,
+    written: usize,
+}
+
+impl NetworkSink {
+    /// Ship records to a collector over TCP.
+    pub fn tcp<A: ToSocketAddrs>(addr: A) -> io::Result<NetworkSinkBuilder> {
+        NetworkSinkBuilder::new(addr, Protocol::Tcp)
+    }
+
+    /// Ship records to a collector over UDP.
+    pub fn udp<A: ToSocketAddrs>(addr: A) -> io::Result<NetworkSinkBuilder> {
+        NetworkSinkBuilder::new(addr, Protocol::Udp)
+    }
+
+    /// Number of records dropped because the queue was full, they could never be sent, or the
+    /// collector couldn't be reached when the sink was dropped.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Serialise a record according to the framing, delimited when sent over TCP.
+    fn serialise(&self, record: &Record) -> Vec<u8> {
+        let mut payload = match self.framing {
+            Framing::Gelf => gelf(record, &self.host).into_bytes(),
+            Framing::JsonLines => format::json(record).into_bytes(),
+        };
+        match (self.protocol, self.framing) {
+            (Protocol::Tcp, Framing::Gelf) => payload.push(b'\0'),
+            (Protocol::Tcp, Framing::JsonLines) => payload.push(b'\n'),
+            (Protocol::Udp, _) => {}
+        }
+        payload
+    }
+}
+
+impl Sink for NetworkSink {
+    fn log(&self, record: &Record, _formatted: &str) {
+        let payload = self.serialise(record);
+        let mut state = self.shared.lock();
+        if state.queue.len() >= self.shared.capacity
+            && let Some(oldest) = state.queue.pop_front()
+        {
+            oldest.drop_record(&self.shared);
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.queue.push_back(Queued {
+            id,
+            level: record.level,
+            target: record.target.clone(),
+            payload,
+        });
+        self.shared.condvar.notify_one();
+    }
+}
+
+impl Drop for NetworkSink {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.condvar.notify_one();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl NetworkSinkBuilder {
+    fn new<A: ToSocketAddrs>(addr: A, protocol: Protocol) -> io::Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no address to send to",
+            ));
+        }
+
+        Ok(Self {
+            addrs,
+            protocol,
+            framing: Framing::default(),
+            capacity: 1024,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+            host: env::hostname().unwrap_or_else(|| String::from("localhost")),
+        })
+    }
+
+    /// Set how records are serialised.
+    /// Default: Framing::Gelf.
+    pub fn set_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Set how many records are buffered while the collector can't be reached.
+    /// Default: 1024.
+    pub fn set_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Set the delay before the first reconnection attempt, and the most it doubles up to.
+    /// Default: 100ms, up to 30s.
+    pub fn set_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Set how long connecting, or writing a record over TCP, may take before it is retried.
+    /// Also bounds how long dropping the sink waits for an unreachable collector.
+    /// Default: 5s.
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Set the host reported in GELF messages.
+    /// Default: env::hostname.
+    pub fn set_host<T: Into<String>>(mut self, host: T) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Done building, start the background thread and return the sink.
+    pub fn done(self) -> io::Result<NetworkSink> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                next_id: 0,
+                closed: false,
+            }),
+            condvar: Condvar::new(),
+            dropped: AtomicU64::new(0),
+            capacity: self.capacity,
+        });
+        let protocol = self.protocol;
+        let framing = self.framing;
+        let host = self.host.clone();
+        let worker_shared = Arc::clone(&shared);
+        let worker = thread::Builder::new()
+            .name(String::from("std_ex-network-sink"))
+            .spawn(move || self.run(&worker_shared))?;
+
+        Ok(NetworkSink {
+            shared,
+            protocol,
+            framing,
+            host,
+            worker: Some(worker),
+        })
+    }
+
+    /// Send queued records until the sink is dropped.
+    fn run(&self, shared: &Shared) {
+        let mut connection: Option<Connection> = None;
+        let mut partial: Option<Partial> = None;
+        let mut backoff = self.initial_backoff;
+
+        loop {
+            let (id, payload, closed) = {
+                let mut state = shared.lock();
+                while state.queue.is_empty() && !state.closed {
+                    state = shared.wait(state);
+                }
+                match state.queue.front() {
+                    Some(queued) => (queued.id, queued.payload.clone(), state.closed),
+                    None => return,
+                }
+            };
+
+            // The connection has part of a record which has since been dropped from the queue,
+            // so it can't be finished, and the collector discards it along with the connection.
+            let mut written = match partial.take() {
+                Some(partial) if partial.id == id => partial.written,
+                Some(_) => {
+                    connection = None;
+                    0
+                }
+                None => 0,
+            };
+            let sent = match connection.as_mut() {
+                Some(connection) => self.send(connection, &payload, &mut written),
+                None => match self.connect() {
+                    Ok(new) => self.send(connection.insert(new), &payload, &mut written),
+                    Err(error) => Err(error),
+                },
+            };
+
+            match sent {
+                Ok(()) => {
+                    backoff = self.initial_backoff;
+                    let mut state = shared.lock();
+                    if state.queue.front().is_some_and(|front| front.id == id) {
+                        state.queue.pop_front();
+                    }
+                }
+                Err(error) if is_permanent(&error) => {
+                    if written > 0 {
+                        connection = None;
+                    }
+                    let mut state = shared.lock();
+                    if state.queue.front().is_some_and(|front| front.id == id)
+                        && let Some(queued) = state.queue.pop_front()
+                    {
+                        queued.drop_record(shared);
+                    }
+                }
+                Err(_) if closed => {
+                    let mut state = shared.lock();
+                    for queued in state.queue.drain(..) {
+                        queued.drop_record(shared);
+                    }
+                    return;
+                }
+                Err(error) => {
+                    // A write which timed out leaves the connection usable, so the rest of the
+                    // record is written to it. A new connection is sent the whole record.
+                    match is_timeout(&error) && connection.is_some() {
+                        true => partial = Some(Partial { id, written }),
+                        false => connection = None,
+                    }
+                    let state = shared.lock();
+                    let _ = shared
+                        .condvar
+                        .wait_timeout_while(state, backoff, |state| !state.closed);
+                    backoff = (backoff * 2).min(self.max_backoff);
+                }
+            }
+        }
+    }
+
+    fn connect(&self) -> io::Result<Connection> {
+        match self.protocol {
+            Protocol::Tcp => {
+                let mut last_error = None;
+                for addr in &self.addrs {
+                    match TcpStream::connect_timeout(addr, self.timeout) {
+                        Ok(stream) => {
+                            stream.set_write_timeout(Some(self.timeout))?;
+                            return Ok(Connection::Tcp(stream));
+                        }
+                        Err(error) => last_error = Some(error),
+                    }
+                }
+                Err(last_error.unwrap_or_else(|| io::Error::from(io::ErrorKind::NotConnected)))
+            }
+            Protocol::Udp => {
+                let addr = self.addrs[0];
+                let bind_addr = match addr.is_ipv4() {
+                    true => "0.0.0.0:0",
+                    false => "[::]:0",
+                };
+                let socket = UdpSocket::bind(bind_addr)?;
+                socket.connect(addr)?;
+                Ok(Connection::Udp(socket))
+            }
+        }
+    }
+
+    /// Send a record, continuing over TCP from the bytes already written.
+    fn send(
+        &self,
+        connection: &mut Connection,
+        payload: &[u8],
+        written: &mut usize,
+    ) -> io::Result<()> {
+        match (connection, self.framing) {
+            (Connection::Tcp(stream), _) => write_from(stream, payload, written),
+            (Connection::Udp(socket), Framing::Gelf) => {
+                for datagram in gelf_chunks(payload, message_id())? {
+                    socket.send(&datagram)?;
+                }
+                Ok(())
+            }
+            (Connection::Udp(_), Framing::JsonLines) if payload.len() > UDP_MAX_PAYLOAD => {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "record too large for a datagram",
+                ))
+            }
+            (Connection::Udp(socket), Framing::JsonLines) => socket.send(payload).map(|_| ()),
+        }
+    }
+}
+
+/// Write the rest of the payload, from the bytes already written, counting each write so a
+/// timed out write can be continued.
+fn write_from<W: Write>(writer: &mut W, payload: &[u8], written: &mut usize) -> io::Result<()> {
+    while *written < payload.len() {
+        match writer.write(&payload[*written..]) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+            Ok(n) => *written += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    writer.flush()
+}
+
+/// Errors sending a record which retrying can't fix, so the record is dropped.
+fn is_permanent(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData
+    )
+}
+
+/// Errors from a write which took too long, after which the connection can still be used.
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.condvar
+            .wait(state)
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Serialise a record as a GELF 1.1 message.
+fn gelf(record: &Record, host: &str) -> String {
+    let mut output = String::from("{\"version\":\"1.1\",\"host\":");
+    format::write_json_string(&mut output, host);
+    output.push_str(",\"short_message\":");
+    format::write_json_string(&mut output, &record.msg);
+    if record.data.is_some() || !record.errors.is_empty() || !record.context.is_empty() {
+        output.push_str(",\"full_message\":");
+        format::write_json_string(&mut output, &syslog::message(record, false));
+    }
+    let timestamp = record.time.timestamp_millis() as f64 / 1000.0;
+    let _ = write!(
+        output,
+        ",\"timestamp\":{timestamp:.3},\"level\":{},\"_file\":",
+        syslog_severity(record.level)
+    );
+    format::write_json_string(&mut output, record.location.file());
+    let _ = write!(output, ",\"_line\":{}", record.location.line());
+
+    if !record.target.is_empty() {
+        output.push_str(",\"_target\":");
+        format::write_json_string(&mut output, &record.target);
+    }
+    let prefix = record.prefix.trim();
+    if !prefix.is_empty() {
+        output.push_str(",\"_prefix\":");
+        format::write_json_string(&mut output, prefix);
+    }
+    for (key, value) in &record.fields {
+        output.push(',');
+        format::write_json_string(&mut output, &gelf_field_name(key));
+        output.push(':');
+        format::write_json_string(&mut output, value);
+    }
+
+    output.push('}');
+    output
+}
+
+/// GELF additional fields are prefixed with an underscore, limited to word characters, dots and
+/// dashes, and may not be `_id`.
+fn gelf_field_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    match name.as_str() {
+        "id" => String::from("_id_"),
+        _ => format!("_{name}"),
+    }
+}
+
+/// Split a GELF message into UDP datagrams, chunking it when it is too large for one.
+fn gelf_chunks(payload: &[u8], message_id: [u8; 8]) -> io::Result<Vec<Vec<u8>>> {
+    if payload.len() <= GELF_CHUNK_SIZE {
+        return Ok(vec![payload.to_vec()]);
+    }
+
+    let chunks: Vec<&[u8]> = payload
+        .chunks(GELF_CHUNK_SIZE - GELF_CHUNK_HEADER_SIZE)
+        .collect();
+    if chunks.len() > GELF_MAX_CHUNKS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "gelf message too large to chunk",
+        ));
+    }
+
+    let count = chunks.len() as u8;
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(sequence, chunk)| {
+            let mut datagram = Vec::with_capacity(GELF_CHUNK_HEADER_SIZE + chunk.len());
+            datagram.extend_from_slice(&GELF_CHUNK_MAGIC);
+            datagram.extend_from_slice(&message_id);
+            datagram.push(sequence as u8);
+            datagram.push(count);
+            datagram.extend_from_slice(chunk);
+            datagram
+        })
+        .collect())
+}
+
+/// A message id for GELF chunks, unique enough to tell concurrent messages apart.
+fn message_id() -> [u8; 8] {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    (nanos ^ COUNTER.fetch_add(1, Ordering::Relaxed).rotate_left(32)).to_be_bytes()
+}
+
+/// Sockets for the tests of the network sinks, which fail rather than hang when nothing is sent.
+#[cfg(test)]
+pub(crate) mod test_sockets {
+    use crate::io;
+    use crate::net::{TcpListener, TcpStream, UdpSocket};
+    use crate::thread;
+    use crate::time::{Duration, Instant};
+
+    /// How long a test waits for a connection or a read.
+    pub(crate) const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// The next connection to the listener, whose reads time out.
+    pub(crate) fn accept(listener: &TcpListener) -> TcpStream {
+        listener.set_nonblocking(true).unwrap();
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false).unwrap();
+                    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+                    return stream;
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    assert!(
+                        Instant::now() < deadline,
+                        "no connection within {TIMEOUT:?}"
+                    );
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(error) => panic!("{error}"),
+            }
+        }
+    }
+
+    /// A UDP socket on a free local port, whose reads time out.
+    pub(crate) fn udp_server() -> UdpSocket {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(TIMEOUT)).unwrap();
+        server
+    }
+}
+
+#[cfg(test)]
+mod network_tests {
+    use super::*;
+    use crate::io::{BufRead, BufReader, Read};
+    use crate::log::network::test_sockets::{accept, udp_server};
+    use crate::log::{Log, LogLevel, static_level};
+    use crate::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn should_ship_json_lines_over_tcp() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink = NetworkSink::tcp(listener.local_addr().unwrap())
+            .unwrap()
+            .set_framing(Framing::JsonLines)
+            .done()
+            .unwrap();
+        let log = Log::new_ex().set_sink(sink).done();
+        log.error("first");
+        log.errorf("second", &1);
+
+        let stream = accept(&listener);
+        let lines: Vec<String> = BufReader::new(stream)
+            .lines()
+            .take(2)
+            .map(Result::unwrap)
+            .collect();
+        assert!(lines[0].contains(r#""message":"first""#));
+        assert!(lines[1].contains(r#""message":"second","data":"1""#));
+    }
+
+    #[test]
+    fn should_ship_null_terminated_gelf_over_tcp() {
+        if !static_level::enabled(LogLevel::Warn) {
+            return;
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink = NetworkSink::tcp(listener.local_addr().unwrap())
+            .unwrap()
+            .set_host("host")
+            .done()
+            .unwrap();
+        let log = Log::new_ex()
+            .set_sink(sink)
+            .done()
+            .child_with_field("id", 7);
+        log.warn("gelf");
+        drop(log);
+
+        let mut stream = accept(&listener);
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        assert!(received.ends_with("\"_id_\":\"7\"}\0"));
+        assert!(
+            received.starts_with(
+                r#"{"version":"1.1","host":"host","short_message":"gelf","timestamp":"#
+            )
+        );
+        assert!(received.contains(r#""level":4,"_file":"#));
+    }
+
+    #[test]
+    fn should_buffer_while_unreachable_and_count_dropped_records() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        // Nothing listens on the client's port, and it stays reserved while the client is held.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let _server = accept(&listener);
+        let unreachable = client.local_addr().unwrap();
+
+        let sink = Arc::new(
+            NetworkSink::tcp(unreachable)
+                .unwrap()
+                .set_framing(Framing::JsonLines)
+                .set_capacity(2)
+                .set_backoff(Duration::from_millis(10), Duration::from_millis(20))
+                .done()
+                .unwrap(),
+        );
+        let log = Log::new_ex()
+            .set_log_level(LogLevel::Trace)
+            .set_sink(Arc::clone(&sink))
+            .done();
+        log.error("first");
+        log.error("second");
+        log.error("third");
+        assert_eq!(sink.dropped(), 1);
+
+        drop(log);
+        let sink = Arc::into_inner(sink).unwrap();
+        let shared = Arc::clone(&sink.shared);
+        drop(sink);
+        assert_eq!(shared.dropped.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn should_drop_records_which_can_never_be_sent() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let server = udp_server();
+        let sink = Arc::new(
+            NetworkSink::udp(server.local_addr().unwrap())
+                .unwrap()
+                .set_framing(Framing::JsonLines)
+                .done()
+                .unwrap(),
+        );
+        let log = Log::new_ex().set_sink(Arc::clone(&sink)).done();
+        log.error("a".repeat(UDP_MAX_PAYLOAD));
+        log.error("after");
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).contains(r#""message":"after""#));
+        assert_eq!(sink.dropped(), 1);
+    }
+
+    #[test]
+    fn should_continue_a_timed_out_write_from_the_unsent_bytes() {
+        /// Takes a few bytes at a time, timing out every other write.
+        struct Slow {
+            received: Vec<u8>,
+            calls: usize,
+        }
+        impl Write for Slow {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.calls += 1;
+                if self.calls.is_multiple_of(2) {
+                    return Err(io::Error::from(io::ErrorKind::TimedOut));
+                }
+                let n = buf.len().min(3);
+                self.received.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut slow = Slow {
+            received: Vec::new(),
+            calls: 0,
+        };
+        let mut written = 0;
+        let payload = b"{\"message\":\"slow\"}\n";
+        while let Err(error) = write_from(&mut slow, payload, &mut written) {
+            assert!(is_timeout(&error));
+        }
+        assert_eq!(written, payload.len());
+        assert_eq!(slow.received, payload);
+    }
+
+    #[test]
+    fn should_ship_json_over_udp() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let server = udp_server();
+        let sink = NetworkSink::udp(server.local_addr().unwrap())
+            .unwrap()
+            .set_framing(Framing::JsonLines)
+            .done()
+            .unwrap();
+        let log = Log::new_ex().set_sink(sink).done();
+        log.error("datagram");
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).contains(r#""message":"datagram""#));
+    }
+
+    #[test]
+    fn should_chunk_large_gelf_messages() {
+        let payload = vec![b'a'; GELF_CHUNK_SIZE * 2];
+        let chunks = gelf_chunks(&payload, [1; 8]).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= GELF_CHUNK_SIZE));
+        assert_eq!(
+            &chunks[2][..12],
+            &[0x1e, 0x0f, 1, 1, 1, 1, 1, 1, 1, 1, 2, 3]
+        );
+        let joined: usize = chunks.iter().map(|chunk| chunk.len() - 12).sum();
+        assert_eq!(joined, payload.len());
+
+        assert_eq!(
+            gelf_chunks(b"small", [1; 8]).unwrap(),
+            vec![b"small".to_vec()]
+        );
+        let too_large = vec![b'a'; GELF_CHUNK_SIZE * GELF_MAX_CHUNKS + 1];
+        assert!(gelf_chunks(&too_large, [1; 8]).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use crate::collections::{HashMap, HashSet};
+use crate::log::LogLevel;
+use crate::panic::Location;
+use crate::sync::{LazyLock, Mutex, MutexGuard};
+use crate::time::{Duration, Instant};
+
+/// Most buckets kept before idle ones are forgotten, so per message limits can't grow forever.
+const MAX_BUCKETS: usize = 1024;
+
+/// Call sites which have logged with one of the `_once` methods.
+static ONCE_SITES: LazyLock<Mutex<HashSet<CallSite>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// What records are grouped by when rate limiting.
+/// RateLimitKey::CallSite -> Each place in the source that logs has its own limit.
+/// RateLimitKey::Message -> Each distinct message has its own limit, wherever it is logged from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitKey {
+    #[default]
+    CallSite,
+    Message,
+}
+
+/// A token bucket limit on how often records are logged.
+///
+/// Each key starts with `burst` tokens, and one is spent per record. Tokens refill evenly, so
+/// `burst` records are allowed every `per`. Records logged without a token are dropped, and the
+/// number dropped is added as a `suppressed` field to the next record allowed for that key.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use std_ex::log::{Log, RateLimit, RateLimitKey};
+///
+/// let log = Log::new_ex()
+///     .set_rate_limit(RateLimit::new(10, Duration::from_secs(60)).set_key(RateLimitKey::Message))
+///     .done();
+/// for _ in 0..1000 {
+///     log.warn("retrying"); // only 10 are written
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    burst: u32,
+    per: Duration,
+    key: RateLimitKey,
+}
+
+impl RateLimit {
+    /// Allow up to burst records every per, for each call site.
+    pub fn new(burst: u32, per: Duration) -> Self {
+        Self {
+            burst: burst.max(1),
+            per,
+            key: RateLimitKey::default(),
+        }
+    }
+
+    /// Set what records are grouped by.
+    /// Default: RateLimitKey::CallSite.
+    pub fn set_key(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CallSite {
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl From<&Location<'static>> for CallSite {
+    fn from(location: &Location<'static>) -> Self {
+        Self {
+            file: location.file(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    CallSite(CallSite),
+    Message(LogLevel, String),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    suppressed: u64,
+}
+
+/// The shared state of a RateLimit, one bucket per key.
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<Key, Bucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Are records keyed by their message, so it has to be formatted before they are checked.
+    pub(crate) fn keys_by_message(&self) -> bool {
+        self.limit.key == RateLimitKey::Message
+    }
+
+    /// Take a token for a record logged at the location, checked before the record is built so
+    /// dropped records aren't formatted. msg is only used when keys_by_message.
+    /// Returns None if the record should be dropped, otherwise how many were dropped before it.
+    pub(crate) fn check(
+        &self,
+        location: &'static Location<'static>,
+        level: LogLevel,
+        msg: &str,
+    ) -> Option<u64> {
+        self.check_at(location, level, msg, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        location: &'static Location<'static>,
+        level: LogLevel,
+        msg: &str,
+        now: Instant,
+    ) -> Option<u64> {
+        let key = match self.limit.key {
+            RateLimitKey::CallSite => Key::CallSite(CallSite::from(location)),
+            RateLimitKey::Message => Key::Message(level, msg.to_string()),
+        };
+        let burst = f64::from(self.limit.burst);
+        let mut buckets = lock(&self.buckets);
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| {
+                bucket.suppressed > 0 || self.refill(bucket.tokens, bucket.updated, now) < burst
+            });
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            suppressed: 0,
+        });
+        bucket.tokens = self.refill(bucket.tokens, bucket.updated, now);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            bucket.suppressed += 1;
+            return None;
+        }
+
+        bucket.tokens -= 1.0;
+        Some(crate::mem::take(&mut bucket.suppressed))
+    }
+
+    /// Tokens in a bucket after refilling since it was last updated.
+    fn refill(&self, tokens: f64, updated: Instant, now: Instant) -> f64 {
+        let burst = f64::from(self.limit.burst);
+        let elapsed = now.saturating_duration_since(updated).as_secs_f64();
+        match self.limit.per.is_zero() {
+            true => burst,
+            false => (tokens + elapsed * burst / self.limit.per.as_secs_f64()).min(burst),
+        }
+    }
+}
+
+/// Is this the first time a record has been logged from the call site, marking it if so.
+pub(crate) fn first_at(location: &'static Location<'static>) -> bool {
+    lock(&ONCE_SITES).insert(CallSite::from(location))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod rate_limit_tests {
+    use super::*;
+    use crate::log::Record;
+
+    fn check_at(limiter: &RateLimiter, record: &Record, now: Instant) -> Option<u64> {
+        limiter.check_at(record.location, record.level, &record.msg, now)
+    }
+
+    #[test]
+    fn should_allow_burst_then_refill_evenly() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(10)));
+        let record = Record::test(LogLevel::Warn, "retrying");
+        let start = Instant::now();
+        assert_eq!(check_at(&limiter, &record, start), Some(0));
+        assert_eq!(check_at(&limiter, &record, start), Some(0));
+        assert_eq!(check_at(&limiter, &record, start), None);
+        assert_eq!(
+            check_at(&limiter, &record, start + Duration::from_secs(4)),
+            None
+        );
+        assert_eq!(
+            check_at(&limiter, &record, start + Duration::from_secs(5)),
+            Some(2)
+        );
+        assert_eq!(
+            check_at(&limiter, &record, start + Duration::from_secs(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn should_limit_each_call_site_separately() {
+        let limiter = RateLimiter::new(RateLimit::new(1, Duration::from_secs(60)));
+        let now = Instant::now();
+        assert_eq!(
+            check_at(&limiter, &Record::test(LogLevel::Warn, "a"), now),
+            Some(0)
+        );
+        assert_eq!(
+            check_at(&limiter, &Record::test(LogLevel::Warn, "a"), now),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn should_limit_each_message_separately() {
+        let limiter = RateLimiter::new(
+            RateLimit::new(1, Duration::from_secs(60)).set_key(RateLimitKey::Message),
+        );
+        let now = Instant::now();
+        assert_eq!(
+            check_at(&limiter, &Record::test(LogLevel::Warn, "a"), now),
+            Some(0)
+        );
+        assert_eq!(
+            check_at(&limiter, &Record::test(LogLevel::Warn, "b"), now),
+            Some(0)
+        );
+        assert_eq!(
+            check_at(&limiter, &Record::test(LogLevel::Warn, "a"), now),
+            None
+        );
+    }
+
+    #[test]
+    fn should_mark_call_sites_once() {
+        let location = Location::caller();
+        assert!(first_at(location));
+        assert!(!first_at(location));
+    }
+}
@@ -0,0 +1,50 @@
+use crate::chrono;
+use crate::log::LogLevel;
+use crate::panic::Location;
+
+/// A single log event, captured once per call and handed to the output format and any sinks.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Record {
+    pub level: LogLevel,
+    pub time: chrono::DateTime<chrono::Local>,
+    /// Where in the source the log was called from.
+    pub location: &'static Location<'static>,
+    /// Dotted path of the named logger, e.g. `app.db.pool`, empty if it has no name.
+    pub target: String,
+    /// Prefix added by child loggers, may have leading whitespace.
+    pub prefix: String,
+    pub msg: String,
+    /// Pretty printed data passed to logf.
+    pub data: Option<String>,
+    /// The error passed to error_chain followed by each of its sources, empty for other logs.
+    pub errors: Vec<String>,
+    /// Backtrace captured by error_chain, if backtraces are enabled.
+    pub backtrace: Option<String>,
+    /// Pretty printed context of the logger, empty if there is none.
+    pub context: String,
+    /// Key value pairs added to the logger.
+    pub fields: Vec<(String, String)>,
+}
+
+#[cfg(test)]
+impl Record {
+    /// A record logged now from the caller, with only a level and message, for tests to build on
+    /// with struct update syntax.
+    #[track_caller]
+    pub(crate) fn test(level: LogLevel, msg: &str) -> Self {
+        Self {
+            level,
+            time: chrono::Local::now(),
+            location: Location::caller(),
+            target: String::new(),
+            prefix: String::new(),
+            msg: String::from(msg),
+            data: None,
+            errors: Vec::new(),
+            backtrace: None,
+            context: String::new(),
+            fields: Vec::new(),
+        }
+    }
+}
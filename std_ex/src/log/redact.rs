@@ -0,0 +1,657 @@
+use crate::borrow::Cow;
+use crate::log::Record;
+use crate::str::{Chars, FromStr};
+use crate::{error::Error, fmt, fmt::Display, iter::Peekable};
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "***";
+
+/// The field names redacted by Redaction::default.
+pub const DEFAULT_REDACTED_FIELDS: [&str; 10] = [
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "api_key",
+    "authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// A value which is written as `***` by both Debug and Display, so it can be passed to logf or
+/// kept in a struct which is logged without leaking it.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::Redacted;
+///
+/// #[derive(Debug)]
+/// struct Login {
+///     user: String,
+///     password: Redacted<String>,
+/// }
+///
+/// let login = Login {
+///     user: String::from("admin"),
+///     password: Redacted(String::from("hunter2")),
+/// };
+/// assert_eq!(format!("{login:?}"), r#"Login { user: "admin", password: *** }"#);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Redacted<T>(pub T);
+
+impl<T> Redacted<T> {
+    /// The wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Which values are replaced with `***` before a record is written, in every format and sink.
+///
+/// Field names are matched against the logger's fields, and the fields and map keys of data and
+/// context pretty printed by logf, logp and child_with_*_context. A name matches when it ends with
+/// a redacted name starting at a word, ignoring case, `_` and `-`, so `api_key` also matches
+/// `apiKey`, `API-KEY` and `X-Api-Key`, and `password` matches `db_password`, but `id` doesn't
+/// match `paid`. Patterns are matched against the message, data,
+/// context and field values, and only the matching text is replaced.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::{Log, Redaction};
+///
+/// let redaction = Redaction::default()
+///     .add_field("session")
+///     .add_pattern(r"Bearer \S+".parse().unwrap());
+/// let log = Log::new_ex().set_redaction(redaction).done();
+/// log.info("sent Bearer abc.def"); // INFO: sent ***
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Redaction {
+    fields: Vec<String>,
+    patterns: Vec<RedactionPattern>,
+}
+
+impl Default for Redaction {
+    /// Redact DEFAULT_REDACTED_FIELDS.
+    fn default() -> Self {
+        DEFAULT_REDACTED_FIELDS
+            .iter()
+            .fold(Self::empty(), |redaction, field| redaction.add_field(field))
+    }
+}
+
+impl Redaction {
+    /// Redact nothing, for building up a list without the defaults.
+    pub fn empty() -> Self {
+        Self {
+            fields: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Redact the values of fields with the name.
+    pub fn add_field(mut self, name: &str) -> Self {
+        let name = normalise(name);
+        if !self.fields.contains(&name) {
+            self.fields.push(name);
+        }
+        self
+    }
+
+    /// Redact text matching the pattern, wherever it appears.
+    pub fn add_pattern(mut self, pattern: RedactionPattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Redact every part of a record.
+    pub(crate) fn apply(&self, record: &mut Record) {
+        let redact = |s: &mut String| {
+            if let Cow::Owned(redacted) = self.patterns(s) {
+                *s = redacted;
+            }
+        };
+        for (key, value) in record.fields.iter_mut() {
+            match self.is_redacted(key) {
+                true => *value = String::from(REDACTED),
+                false => redact(value),
+            }
+        }
+        redact(&mut record.msg);
+        record.errors.iter_mut().for_each(redact);
+        let text = |s: &str| -> Option<String> {
+            match self.pretty(s) {
+                Cow::Borrowed(s) => match self.patterns(s) {
+                    Cow::Borrowed(_) => None,
+                    Cow::Owned(s) => Some(s),
+                },
+                Cow::Owned(s) => Some(self.patterns(&s).into_owned()),
+            }
+        };
+        if let Some(data) = record.data.as_mut()
+            && let Some(redacted) = text(data)
+        {
+            *data = redacted;
+        }
+        if let Some(redacted) = text(&record.context) {
+            record.context = redacted;
+        }
+    }
+
+    fn is_redacted(&self, name: &str) -> bool {
+        !self.fields.is_empty()
+            && word_starts(name).any(|start| self.fields.contains(&normalise(&name[start..])))
+    }
+
+    /// Replace every match of every pattern.
+    fn patterns<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.patterns.iter().fold(Cow::Borrowed(s), |s, pattern| {
+            match pattern.replace_all(&s) {
+                Cow::Borrowed(_) => s,
+                Cow::Owned(replaced) => Cow::Owned(replaced),
+            }
+        })
+    }
+
+    /// Replace the values of redacted fields in `{:#?}` output, including any nested lines.
+    fn pretty<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        if self.fields.is_empty() {
+            return Cow::Borrowed(s);
+        }
+
+        let mut output = String::new();
+        let mut changed = false;
+        let mut lines = s.lines();
+        while let Some(line) = lines.next() {
+            let Some((indent, key, value)) = split_field(line) else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+            if !self.is_redacted(key) {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+
+            changed = true;
+            output.push_str(&line[..indent + key.len() + 2]);
+            output.push_str(REDACTED);
+            if value.ends_with(['{', '[', '(']) {
+                // skip the nested lines, up to the closing line at the same indent
+                for nested in lines.by_ref() {
+                    let nested_indent = nested.len() - nested.trim_start().len();
+                    if nested_indent == indent && nested.trim_start().starts_with(['}', ']', ')']) {
+                        if nested.ends_with(',') {
+                            output.push(',');
+                        }
+                        break;
+                    }
+                }
+            } else if value.ends_with(',') {
+                output.push(',');
+            }
+            output.push('\n');
+        }
+
+        match changed {
+            true => {
+                if !s.ends_with('\n') {
+                    output.pop();
+                }
+                Cow::Owned(output)
+            }
+            false => Cow::Borrowed(s),
+        }
+    }
+}
+
+/// Split a line of `{:#?}` output into the indent, key and value, if it is a struct field or map
+/// entry with a string key.
+fn split_field(line: &str) -> Option<(usize, &str, &str)> {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let key_len = match trimmed.strip_prefix('"') {
+        Some(rest) => rest.find('"')? + 2,
+        None => trimmed
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(trimmed.len()),
+    };
+    let (key, rest) = trimmed.split_at(key_len);
+    let value = rest.strip_prefix(": ")?;
+    match key.is_empty() {
+        true => None,
+        false => Some((indent, key, value)),
+    }
+}
+
+/// The byte offsets where each word of a name starts, splitting on anything other than letters and
+/// digits, and on camel case, e.g. `xApi-KEY` has words at 0, 1 and 5, and `HTTPToken` at 0 and 4.
+fn word_starts(name: &str) -> impl Iterator<Item = usize> + '_ {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    (0..chars.len()).filter_map(move |i| {
+        let (start, c) = chars[i];
+        let prev = i.checked_sub(1).map(|prev| chars[prev].1);
+        let next = chars.get(i + 1).map(|(_, next)| *next);
+        let starts_word = match prev {
+            None => true,
+            Some(prev) if !prev.is_alphanumeric() => true,
+            Some(prev) if c.is_uppercase() && !prev.is_uppercase() => true,
+            Some(_) => c.is_uppercase() && next.is_some_and(char::is_lowercase),
+        };
+        (c.is_alphanumeric() && starts_word).then_some(start)
+    })
+}
+
+/// Lowercase, without quotes, `_` or `-`.
+fn normalise(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '"' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// A regex-like pattern for text to redact.
+///
+/// Supports literal characters, `.` for any character, classes such as `[a-z0-9_]` and `[^,]`,
+/// the escapes `\d`, `\w`, `\s` and their negations `\D`, `\W`, `\S`, and the quantifiers `*`,
+/// `+`, `?`, `{n}`, `{n,}` and `{n,m}`. Any other escaped character is literal, e.g. `\.`.
+/// Groups, alternation and anchors are not supported. Quantifiers are greedy, and the leftmost
+/// longest match is replaced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedactionPattern {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRedactionPatternError {
+    reason: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Piece {
+    atom: Atom,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Atom {
+    Any,
+    Char(char),
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+}
+
+impl ParseRedactionPatternError {
+    fn new<T: Into<String>>(reason: T) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ParseRedactionPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid redaction pattern: {}", self.reason)
+    }
+}
+
+impl Error for ParseRedactionPatternError {}
+
+impl FromStr for RedactionPattern {
+    type Err = ParseRedactionPatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars().peekable();
+        let mut pieces = Vec::new();
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '.' => Atom::Any,
+                '[' => parse_class(&mut chars)?,
+                '\\' => parse_escape(&mut chars)?,
+                '*' | '+' | '?' | '{' => {
+                    return Err(ParseRedactionPatternError::new(format!(
+                        "`{c}` has nothing to repeat"
+                    )));
+                }
+                '(' | ')' | '|' | '^' | '$' => {
+                    return Err(ParseRedactionPatternError::new(format!(
+                        "`{c}` is not supported, escape it to match it literally"
+                    )));
+                }
+                c => Atom::Char(c),
+            };
+            let (min, max) = parse_quantifier(&mut chars)?;
+            pieces.push(Piece { atom, min, max });
+        }
+
+        match pieces.is_empty() {
+            true => Err(ParseRedactionPatternError::new("pattern is empty")),
+            false => Ok(Self { pieces }),
+        }
+    }
+}
+
+impl RedactionPattern {
+    /// Replace every non-empty match with `***`.
+    fn replace_all<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let chars: Vec<(usize, char)> = s.char_indices().collect();
+        let text: Vec<char> = chars.iter().map(|(_, c)| *c).collect();
+        let byte = |i: usize| chars.get(i).map_or(s.len(), |(byte, _)| *byte);
+
+        let mut output = String::new();
+        let mut copied = 0;
+        let mut start = 0;
+        while start < text.len() {
+            match match_here(&self.pieces, &text, start) {
+                Some(end) if end > start => {
+                    output.push_str(&s[byte(copied)..byte(start)]);
+                    output.push_str(REDACTED);
+                    copied = end;
+                    start = end;
+                }
+                _ => start += 1,
+            }
+        }
+
+        match copied {
+            0 => Cow::Borrowed(s),
+            _ => {
+                output.push_str(&s[byte(copied)..]);
+                Cow::Owned(output)
+            }
+        }
+    }
+}
+
+/// The end of the longest match of the pieces starting at pos, trying fewer repeats of each
+/// piece until the rest matches.
+fn match_here(pieces: &[Piece], text: &[char], pos: usize) -> Option<usize> {
+    let Some((piece, rest)) = pieces.split_first() else {
+        return Some(pos);
+    };
+
+    let mut count = 0;
+    while count < piece.max && pos + count < text.len() && piece.atom.matches(text[pos + count]) {
+        count += 1;
+    }
+    loop {
+        if count < piece.min {
+            return None;
+        }
+        if let Some(end) = match_here(rest, text, pos + count) {
+            return Some(end);
+        }
+        if count == 0 {
+            return None;
+        }
+        count -= 1;
+    }
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => c == *expected,
+            Atom::Class { items, negated } => items.iter().any(|item| item.matches(c)) != *negated,
+        }
+    }
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(from, to) => (*from..=*to).contains(&c),
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_alphanumeric() || c == '_',
+            ClassItem::Space => c.is_whitespace(),
+        }
+    }
+}
+
+fn parse_escape(chars: &mut Peekable<Chars>) -> Result<Atom, ParseRedactionPatternError> {
+    let c = chars
+        .next()
+        .ok_or_else(|| ParseRedactionPatternError::new("pattern ends with `\\`"))?;
+    let class = |item, negated| Atom::Class {
+        items: vec![item],
+        negated,
+    };
+    Ok(match c {
+        'd' => class(ClassItem::Digit, false),
+        'D' => class(ClassItem::Digit, true),
+        'w' => class(ClassItem::Word, false),
+        'W' => class(ClassItem::Word, true),
+        's' => class(ClassItem::Space, false),
+        'S' => class(ClassItem::Space, true),
+        c => Atom::Char(c),
+    })
+}
+
+fn parse_class(chars: &mut Peekable<Chars>) -> Result<Atom, ParseRedactionPatternError> {
+    let negated = chars.next_if_eq(&'^').is_some();
+    let mut items = Vec::new();
+    loop {
+        let c = chars
+            .next()
+            .ok_or_else(|| ParseRedactionPatternError::new("unclosed `[`"))?;
+        let from = match c {
+            ']' if !items.is_empty() => break,
+            '\\' => match parse_escape(chars)? {
+                Atom::Class {
+                    items: escaped,
+                    negated: false,
+                } => {
+                    items.extend(escaped);
+                    continue;
+                }
+                Atom::Char(c) => c,
+                _ => {
+                    return Err(ParseRedactionPatternError::new(
+                        "negated escapes are not supported in classes",
+                    ));
+                }
+            },
+            c => c,
+        };
+        let to = match chars.peek() {
+            Some('-') => {
+                chars.next();
+                match chars.next() {
+                    Some(']') => {
+                        items.push(ClassItem::Range(from, from));
+                        items.push(ClassItem::Range('-', '-'));
+                        break;
+                    }
+                    Some(to) if to >= from => to,
+                    Some(to) => {
+                        return Err(ParseRedactionPatternError::new(format!(
+                            "range `{from}-{to}` is backwards"
+                        )));
+                    }
+                    None => return Err(ParseRedactionPatternError::new("unclosed `[`")),
+                }
+            }
+            _ => from,
+        };
+        items.push(ClassItem::Range(from, to));
+    }
+
+    Ok(Atom::Class { items, negated })
+}
+
+fn parse_quantifier(
+    chars: &mut Peekable<Chars>,
+) -> Result<(usize, usize), ParseRedactionPatternError> {
+    let quantifier = match chars.peek() {
+        Some('*') => (0, usize::MAX),
+        Some('+') => (1, usize::MAX),
+        Some('?') => (0, 1),
+        Some('{') => {
+            chars.next();
+            let mut spec = String::new();
+            for c in chars.by_ref() {
+                if c == '}' {
+                    return parse_repeat(&spec);
+                }
+                spec.push(c);
+            }
+            return Err(ParseRedactionPatternError::new("unclosed `{`"));
+        }
+        _ => return Ok((1, 1)),
+    };
+    chars.next();
+    Ok(quantifier)
+}
+
+/// Parse the inside of `{n}`, `{n,}` or `{n,m}`.
+fn parse_repeat(spec: &str) -> Result<(usize, usize), ParseRedactionPatternError> {
+    let invalid = || ParseRedactionPatternError::new(format!("invalid repeat `{{{spec}}}`"));
+    let number = |s: &str| s.trim().parse::<usize>().map_err(|_| invalid());
+    let (min, max) = match spec.split_once(',') {
+        None => (number(spec)?, number(spec)?),
+        Some((min, "")) => (number(min)?, usize::MAX),
+        Some((min, max)) => (number(min)?, number(max)?),
+    };
+    match min <= max {
+        true => Ok((min, max)),
+        false => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod redact_tests {
+    use super::*;
+
+    fn pattern(s: &str) -> RedactionPattern {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn should_write_redacted_values_as_stars() {
+        let secret = Redacted("hunter2");
+        assert_eq!(format!("{secret} {secret:?} {secret:#?}"), "*** *** ***");
+        assert_eq!(secret.into_inner(), "hunter2");
+    }
+
+    #[test]
+    fn should_replace_pattern_matches() {
+        let bearer = pattern(r"Bearer \S+");
+        assert_eq!(bearer.replace_all("auth Bearer abc.def ok"), "auth *** ok");
+        assert_eq!(bearer.replace_all("no token"), "no token");
+        let card = pattern(r"\d{4}[- ]?\d{4}");
+        assert_eq!(
+            card.replace_all("card 1234-5678, 12345678"),
+            "card ***, ***"
+        );
+        let key = pattern("sk_[a-zA-Z0-9]{8,}");
+        assert_eq!(
+            key.replace_all("keys sk_12345678 and sk_123 é"),
+            "keys *** and sk_123 é"
+        );
+        assert_eq!(pattern("a.?c").replace_all("ac abc"), "*** ***");
+    }
+
+    #[test]
+    fn should_reject_invalid_patterns() {
+        for invalid in ["", "*a", "a{2", "a{3,1}", "[a", "[z-a]", "(a)", "a|b", "\\"] {
+            assert!(invalid.parse::<RedactionPattern>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn should_redact_pretty_printed_fields() {
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Inner {
+            token: String,
+        }
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Login {
+            user: &'static str,
+            password: Option<&'static str>,
+            api_key: Inner,
+            nested: Inner,
+        }
+        let login = Login {
+            user: "admin",
+            password: Some("hunter2"),
+            api_key: Inner {
+                token: String::from("abc"),
+            },
+            nested: Inner {
+                token: String::from("def"),
+            },
+        };
+        let redaction = Redaction::default();
+        assert_eq!(
+            redaction.pretty(&fmt::pretty(&login)),
+            "Login {\n    user: \"admin\",\n    password: ***,\n    api_key: ***,\n    nested: Inner {\n        token: ***,\n    },\n}"
+        );
+    }
+
+    #[test]
+    fn should_redact_map_keys_ignoring_case_and_separators() {
+        let map = crate::collections::BTreeMap::from([("API-Key", "abc"), ("user", "admin")]);
+        assert_eq!(
+            Redaction::default().pretty(&fmt::pretty(&map)),
+            "{\n    \"API-Key\": ***,\n    \"user\": \"admin\",\n}"
+        );
+    }
+
+    #[test]
+    fn should_redact_names_ending_with_a_redacted_name() {
+        let redaction = Redaction::default().add_field("id");
+        for name in [
+            "X-Api-Key",
+            "APIKEY",
+            "db_password",
+            "authToken",
+            "HTTPToken",
+            "\"session-id\"",
+        ] {
+            assert!(redaction.is_redacted(name), "{name}");
+        }
+        for name in ["paid", "tokenizer", "password_hint", "user"] {
+            assert!(!redaction.is_redacted(name), "{name}");
+        }
+        assert!(!Redaction::empty().is_redacted("password"));
+    }
+
+    #[test]
+    fn should_leave_text_without_matches_borrowed() {
+        let redaction = Redaction::default().add_pattern(pattern("secret"));
+        assert!(matches!(redaction.pretty("user: 1"), Cow::Borrowed(_)));
+        assert!(matches!(redaction.patterns("public"), Cow::Borrowed(_)));
+    }
+}
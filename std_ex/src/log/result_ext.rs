@@ -0,0 +1,156 @@
+use crate::fmt::Display;
+use crate::log::{LOG, Log, LogLevel};
+use crate::panic::Location;
+
+/// Extension trait for Result to log an error on the way through.
+///
+/// The error is logged at LogLevel::Error as `msg: error`, from the caller's location, and the
+/// result is returned unchanged so it can still be handled or passed on with `?`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::fs;
+/// use std_ex::log::{Log, ResultExt};
+///
+/// fn load(log: &Log) -> std::io::Result<String> {
+///     let config = fs::read_to_string("config.toml").log_err(log, "loading config")?;
+///     Ok(config)
+/// }
+///
+/// assert!(load(&Log::new()).is_err());
+/// ```
+pub trait ResultExt {
+    /// Log the error, if there is one, through the logger.
+    fn log_err<T>(self, log: &Log, msg: T) -> Self
+    where
+        T: AsRef<str> + Display;
+
+    /// Log the error, if there is one, using the global log instance.
+    fn log_err_global<T>(self, msg: T) -> Self
+    where
+        T: AsRef<str> + Display;
+}
+
+/// Extension trait for Option to log when there is no value on the way through.
+///
+/// The message is logged at the given level, from the caller's location, and the option is
+/// returned unchanged.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std_ex::log::{Log, LogLevel, OptionExt};
+///
+/// let headers: HashMap<&str, &str> = HashMap::new();
+/// let log = Log::new();
+/// let host = headers
+///     .get("host")
+///     .log_none(&log, LogLevel::Warn, "missing host header")
+///     .unwrap_or(&"localhost");
+/// ```
+pub trait OptionExt {
+    /// Log the message through the logger if there is no value.
+    fn log_none<T>(self, log: &Log, level: LogLevel, msg: T) -> Self
+    where
+        T: AsRef<str> + Display;
+
+    /// Log the message using the global log instance if there is no value.
+    fn log_none_global<T>(self, level: LogLevel, msg: T) -> Self
+    where
+        T: AsRef<str> + Display;
+}
+
+impl<V, E: Display> ResultExt for Result<V, E> {
+    #[track_caller]
+    fn log_err<T>(self, log: &Log, msg: T) -> Self
+    where
+        T: AsRef<str> + Display,
+    {
+        if let Err(error) = &self {
+            let msg = format!("{msg}: {error}");
+            log.logger_at(LogLevel::Error, &msg, None, Location::caller());
+        }
+        self
+    }
+
+    #[track_caller]
+    fn log_err_global<T>(self, msg: T) -> Self
+    where
+        T: AsRef<str> + Display,
+    {
+        self.log_err(&LOG, msg)
+    }
+}
+
+impl<V> OptionExt for Option<V> {
+    #[track_caller]
+    fn log_none<T>(self, log: &Log, level: LogLevel, msg: T) -> Self
+    where
+        T: AsRef<str> + Display,
+    {
+        if self.is_none() {
+            log.logger_at(level, &msg, None, Location::caller());
+        }
+        self
+    }
+
+    #[track_caller]
+    fn log_none_global<T>(self, level: LogLevel, msg: T) -> Self
+    where
+        T: AsRef<str> + Display,
+    {
+        self.log_none(&LOG, level, msg)
+    }
+}
+
+#[cfg(test)]
+mod result_ext_tests {
+    use super::*;
+    use crate::log::{Capture, static_level};
+    use crate::sync::Arc;
+
+    #[test]
+    fn should_log_error_and_pass_result_through() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let capture = Arc::new(Capture::default());
+        let log = capture.logger();
+        let ok: Result<u8, String> = Ok(1);
+        assert_eq!(ok.log_err(&log, "loading config"), Ok(1));
+        assert!(capture.records().is_empty());
+
+        let err: Result<u8, String> = Err(String::from("not found"));
+        let line = line!() + 1;
+        let result = err.log_err(&log, "loading config");
+        assert_eq!(result, Err(String::from("not found")));
+        assert_eq!(
+            capture.records(),
+            vec![(String::from("ERROR: loading config: not found"), line)]
+        );
+    }
+
+    #[test]
+    fn should_log_none_at_level_and_pass_option_through() {
+        if !static_level::enabled(LogLevel::Warn) {
+            return;
+        }
+        let capture = Arc::new(Capture::default());
+        let log = capture.logger();
+        assert_eq!(
+            Some(1).log_none(&log, LogLevel::Warn, "missing header"),
+            Some(1)
+        );
+        assert!(capture.records().is_empty());
+
+        let line = line!() + 1;
+        let none = None::<u8>.log_none(&log, LogLevel::Warn, "missing header");
+        assert_eq!(none, None);
+        assert_eq!(
+            capture.records(),
+            vec![(String::from("WARN: missing header"), line)]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use crate::collections::VecDeque;
+use crate::io::{self, Write};
+use crate::log::{LogLevel, Record, Sink};
+use crate::panic;
+use crate::sync::{Arc, Mutex, MutexGuard, Once, TryLockError};
+use crate::term::Stream;
+
+/// A sink which keeps the most recent records the logger suppressed in memory, and writes them out
+/// when a record at the trigger level is logged, dump is called, or the program panics.
+///
+/// The sink has its own level, so it can keep trace records from a logger which only prints info
+/// and above. Records the logger writes itself are already in its output, so they aren't kept,
+/// and a dump only adds the context missing from it. Records are kept as formatted by the logger,
+/// and the oldest is dropped once the buffer is full.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std_ex::log::{Log, LogLevel, RingBufferSink};
+///
+/// let recorder = Arc::new(RingBufferSink::new(256).set_trigger(Some(LogLevel::Error)));
+/// recorder.dump_on_panic();
+/// let log = Log::new_ex().add_sink(Arc::clone(&recorder)).done();
+/// log.trace("kept in memory, not printed");
+/// log.info("printed, not kept");
+/// log.error("printed, then the trace above is dumped to stderr");
+/// ```
+pub struct RingBufferSink {
+    records: Mutex<VecDeque<String>>,
+    capacity: usize,
+    level: LogLevel,
+    trigger: Option<LogLevel>,
+    output: Mutex<Box<dyn Write + Send>>,
+    stream: Option<Stream>,
+    panic_hook: Once,
+}
+
+impl RingBufferSink {
+    /// Keep up to capacity records.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            level: LogLevel::Trace,
+            trigger: Some(LogLevel::Error),
+            output: Mutex::new(Box::new(io::stderr())),
+            stream: Some(Stream::Stderr),
+            panic_hook: Once::new(),
+        }
+    }
+
+    /// Set the lowest level kept, regardless of the logger's level.
+    /// Default: LogLevel::Trace.
+    pub fn set_level(mut self, level: LogLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the level at and above which a record dumps the buffer, including that record if the
+    /// logger suppressed it.
+    /// None -> Only dump when asked to.
+    /// Default: Some(LogLevel::Error).
+    pub fn set_trigger(mut self, trigger: Option<LogLevel>) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// Set where dumps are written, e.g. a fs::File.
+    /// Default: stderr.
+    pub fn set_output<W: Write + Send + 'static>(mut self, output: W) -> Self {
+        self.output = Mutex::new(Box::new(output));
+        self.stream = None;
+        self
+    }
+
+    /// The records currently kept, oldest first.
+    pub fn records(&self) -> Vec<String> {
+        lock(&self.records).iter().cloned().collect()
+    }
+
+    /// Write the kept records to the output, oldest first, and clear the buffer.
+    pub fn dump(&self) -> io::Result<()> {
+        let mut output = lock(&self.output);
+        self.dump_to(&mut *output)
+    }
+
+    /// Write the kept records to a writer, oldest first, and clear the buffer.
+    pub fn dump_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let records: Vec<String> = lock(&self.records).drain(..).collect();
+        for record in records {
+            writeln!(writer, "{record}")?;
+        }
+        writer.flush()
+    }
+
+    /// Dump the kept records when the program panics, before the existing panic hook runs.
+    /// Nothing is dumped if the panicking thread was using the sink at the time.
+    /// The hook is only installed the first time this is called for the sink.
+    pub fn dump_on_panic(self: &Arc<Self>) {
+        self.panic_hook.call_once(|| {
+            let sink = Arc::clone(self);
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                let _ = sink.try_dump();
+                previous(info);
+            }));
+        });
+    }
+
+    /// Dump, unless the buffer or output are locked, as waiting could deadlock a thread which
+    /// panicked while holding them.
+    fn try_dump(&self) -> io::Result<()> {
+        let Some(mut output) = try_lock(&self.output) else {
+            return Ok(());
+        };
+        let Some(mut records) = try_lock(&self.records) else {
+            return Ok(());
+        };
+        let records: Vec<String> = records.drain(..).collect();
+        for record in records {
+            writeln!(output, "{record}")?;
+        }
+        output.flush()
+    }
+}
+
+impl RingBufferSink {
+    /// Keep a record the logger suppressed, then dump if it is at the trigger level.
+    fn keep(&self, record: &Record, formatted: &str) {
+        if record.level >= self.level {
+            let mut records = lock(&self.records);
+            if records.len() >= self.capacity {
+                records.pop_front();
+            }
+            records.push_back(formatted.to_string());
+        }
+        self.check_trigger(record);
+    }
+
+    fn check_trigger(&self, record: &Record) {
+        if self.trigger.is_some_and(|trigger| record.level >= trigger) {
+            // A logger has nowhere to report its own failures, so failed dumps are ignored.
+            let _ = self.dump();
+        }
+    }
+}
+
+impl Sink for RingBufferSink {
+    fn log(&self, record: &Record, _formatted: &str) {
+        self.check_trigger(record);
+    }
+
+    fn log_suppressed(&self, record: &Record, formatted: &str) {
+        self.keep(record, formatted);
+    }
+
+    fn level(&self) -> Option<LogLevel> {
+        Some(self.level)
+    }
+
+    fn stream(&self) -> Option<Stream> {
+        self.stream
+    }
+}
+
+/// Lock, recovering the buffer if a thread panicked while holding it, so dumps still work while
+/// panicking.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Lock without waiting, None if another lock is held.
+fn try_lock<T: ?Sized>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+#[cfg(test)]
+mod ring_buffer_tests {
+    use super::*;
+    use crate::log::{Log, static_level};
+
+    /// A writer whose output can be read back while the sink owns it.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            lock(&self.0).extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn text(&self) -> String {
+            String::from_utf8(lock(&self.0).clone()).unwrap()
+        }
+    }
+
+    fn log(sink: &Arc<RingBufferSink>) -> Log {
+        Log::new_ex()
+            .disable_ansi()
+            .set_log_level(LogLevel::Fatal)
+            .set_sink(Arc::clone(sink))
+            .done()
+            .without_env_levels()
+    }
+
+    #[test]
+    fn should_keep_most_recent_records_below_logger_level() {
+        if !static_level::enabled(LogLevel::Trace) {
+            return;
+        }
+        let sink = Arc::new(RingBufferSink::new(2).set_trigger(None));
+        let log = log(&sink);
+        log.trace("first");
+        log.debug("second");
+        log.info("third");
+        assert_eq!(sink.records(), vec!["DEBUG: second", "INFO: third"]);
+    }
+
+    #[test]
+    fn should_ignore_records_below_its_level() {
+        if !static_level::enabled(LogLevel::Trace) {
+            return;
+        }
+        let sink = Arc::new(
+            RingBufferSink::new(4)
+                .set_level(LogLevel::Info)
+                .set_trigger(None),
+        );
+        let log = log(&sink);
+        log.debug("ignored");
+        log.info("kept");
+        assert_eq!(sink.records(), vec!["INFO: kept"]);
+    }
+
+    #[test]
+    fn should_dump_and_clear_when_triggered() {
+        if !static_level::enabled(LogLevel::Trace) {
+            return;
+        }
+        let output = Output::default();
+        let sink = Arc::new(RingBufferSink::new(4).set_output(output.clone()));
+        let log = log(&sink);
+        log.debug("before");
+        log.error("failed");
+        assert_eq!(output.text(), "DEBUG: before\nERROR: failed\n");
+        assert!(sink.records().is_empty());
+    }
+
+    #[test]
+    fn should_only_keep_records_the_logger_suppressed() {
+        if !static_level::enabled(LogLevel::Trace) {
+            return;
+        }
+        let output = Output::default();
+        let sink = Arc::new(RingBufferSink::new(4).set_output(output.clone()));
+        let log = Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(|_| {})
+            .add_sink(Arc::clone(&sink))
+            .done()
+            .without_env_levels();
+        log.debug("before");
+        log.info("printed");
+        log.error("failed");
+        assert_eq!(output.text(), "DEBUG: before\n");
+    }
+
+    #[test]
+    fn should_not_wait_for_locks_held_while_panicking() {
+        let output = Output::default();
+        let sink = RingBufferSink::new(4).set_output(output.clone());
+        lock(&sink.records).push_back(String::from("kept"));
+        {
+            let _held = lock(&sink.output);
+            sink.try_dump().unwrap();
+        }
+        assert_eq!(output.text(), "");
+        sink.try_dump().unwrap();
+        assert_eq!(output.text(), "kept\n");
+    }
+
+    #[test]
+    fn should_install_one_panic_hook_per_sink() {
+        let sink = Arc::new(RingBufferSink::new(1).set_output(Output::default()));
+        sink.dump_on_panic();
+        sink.dump_on_panic();
+        assert_eq!(Arc::strong_count(&sink), 2);
+    }
+
+    #[test]
+    fn should_dump_to_writer() {
+        if !static_level::enabled(LogLevel::Trace) {
+            return;
+        }
+        let sink = Arc::new(RingBufferSink::new(4).set_trigger(None));
+        log(&sink).warn("kept");
+        let mut dumped = Vec::new();
+        sink.dump_to(&mut dumped).unwrap();
+        assert_eq!(dumped, b"WARN: kept\n");
+        assert!(sink.records().is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::log::LogLevel;
+use crate::sync::atomic::{AtomicU64, Ordering};
+use crate::time::{SystemTime, UNIX_EPOCH};
+
+/// Trace through to Silent.
+const LEVELS: usize = 7;
+
+/// How many records at a level are kept.
+/// Sampling::Probability(p) -> Each record is kept at random with probability p, e.g. 0.01 keeps
+/// about 1%.
+/// Sampling::EveryNth(n) -> The first record and every nth after it are kept.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    Probability(f64),
+    EveryNth(u64),
+}
+
+/// The sampling for each level, and the state used to decide which records are kept.
+pub(crate) struct Sampler {
+    rates: [Option<Sampling>; LEVELS],
+    seed: u64,
+    random: AtomicU64,
+    seen: [AtomicU64; LEVELS],
+}
+
+impl Sampler {
+    /// A sampler with no sampling, seeded from the clock.
+    pub(crate) fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with(Default::default(), seed)
+    }
+
+    fn with(rates: [Option<Sampling>; LEVELS], seed: u64) -> Self {
+        Self {
+            rates,
+            seed,
+            // xorshift is stuck at zero, so the state must never start there
+            random: AtomicU64::new((seed ^ 0x9e37_79b9_7f4a_7c15).max(1)),
+            seen: [const { AtomicU64::new(0) }; LEVELS],
+        }
+    }
+
+    /// A new sampler with the level's sampling replaced, starting from a fresh state.
+    pub(crate) fn set_sampling(&self, level: LogLevel, sampling: Sampling) -> Self {
+        let mut rates = self.rates;
+        rates[index(level)] = Some(sampling);
+        Self::with(rates, self.seed)
+    }
+
+    /// A new sampler with the same sampling, seeded so the records kept are the same every run.
+    pub(crate) fn set_seed(&self, seed: u64) -> Self {
+        Self::with(self.rates, seed)
+    }
+
+    /// Should a record at the level be kept.
+    pub(crate) fn keep(&self, level: LogLevel) -> bool {
+        match self.rates[index(level)] {
+            None => true,
+            Some(Sampling::EveryNth(n)) => {
+                let seen = self.seen[index(level)].fetch_add(1, Ordering::Relaxed);
+                seen.is_multiple_of(n.max(1))
+            }
+            Some(Sampling::Probability(probability)) => self.next_f64() < probability,
+        }
+    }
+
+    /// The next number from xorshift64, as a float in [0, 1).
+    fn next_f64(&self) -> f64 {
+        let next = |mut x: u64| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        let previous = self
+            .random
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| Some(next(x)))
+            .unwrap_or_default();
+        (next(previous) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn index(level: LogLevel) -> usize {
+    level as usize - 1
+}
+
+#[cfg(test)]
+mod sampling_tests {
+    use super::*;
+
+    /// The indices of the records kept.
+    fn kept(sampler: &Sampler, level: LogLevel, records: usize) -> Vec<usize> {
+        (0..records).filter(|_| sampler.keep(level)).collect()
+    }
+
+    #[test]
+    fn should_keep_every_record_without_sampling() {
+        let sampler = Sampler::new().set_sampling(LogLevel::Trace, Sampling::EveryNth(2));
+        assert_eq!(kept(&sampler, LogLevel::Warn, 5).len(), 5);
+    }
+
+    #[test]
+    fn should_keep_first_and_every_nth() {
+        let sampler = Sampler::new().set_sampling(LogLevel::Debug, Sampling::EveryNth(3));
+        assert_eq!(kept(&sampler, LogLevel::Debug, 7), vec![0, 3, 6]);
+    }
+
+    #[test]
+    fn should_keep_same_records_with_same_seed() {
+        let sampler = || {
+            Sampler::new()
+                .set_sampling(LogLevel::Trace, Sampling::Probability(0.5))
+                .set_seed(42)
+        };
+        let first = kept(&sampler(), LogLevel::Trace, 100);
+        assert_eq!(first, kept(&sampler(), LogLevel::Trace, 100));
+        assert!(first.len() > 25 && first.len() < 75);
+    }
+
+    #[test]
+    fn should_keep_roughly_the_probability() {
+        let sampler = Sampler::new()
+            .set_sampling(LogLevel::Trace, Sampling::Probability(0.01))
+            .set_seed(7);
+        let count = kept(&sampler, LogLevel::Trace, 100_000).len();
+        assert!((500..1500).contains(&count), "kept {count}");
+
+        let none = Sampler::new().set_sampling(LogLevel::Trace, Sampling::Probability(0.0));
+        assert!(kept(&none, LogLevel::Trace, 100).is_empty());
+        let all = Sampler::new().set_sampling(LogLevel::Trace, Sampling::Probability(1.0));
+        assert_eq!(kept(&all, LogLevel::Trace, 100).len(), 100);
+    }
+}
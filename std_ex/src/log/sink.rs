@@ -0,0 +1,97 @@
+use crate::log::{LogLevel, Record};
+use crate::sync::Arc;
+use crate::term::Stream;
+
+/// A destination for log records, such as syslog or journald.
+///
+/// Sinks receive every record the logger decides to log, and are shared between a logger and
+/// all of its children. Add one with LogrsBuilder::add_sink, or LogrsBuilder::set_sink to replace
+/// the logging function entirely.
+///
+/// A sink with its own level also receives records at that level and above which the logger
+/// would otherwise suppress, such as a RingBufferSink keeping trace records from a logger which
+/// only prints info and above.
+///
+/// Under ColourPolicy::Auto, a sink only gets colour when it writes to a standard stream which
+/// is a terminal, see Sink::stream.
+pub trait Sink: Send + Sync {
+    /// Write a record. `formatted` is the record as rendered by the logger's format.
+    fn log(&self, record: &Record, formatted: &str);
+
+    /// Write a record the logger suppressed, which the sink receives because of its own level.
+    /// Default: Sink::log.
+    fn log_suppressed(&self, record: &Record, formatted: &str) {
+        self.log(record, formatted)
+    }
+
+    /// The lowest level this sink receives, even when below the logger's level.
+    /// None -> Only records the logger logs.
+    fn level(&self) -> Option<LogLevel> {
+        None
+    }
+
+    /// The standard stream this sink writes formatted records to, used to resolve
+    /// ColourPolicy::Auto.
+    /// None -> Not a terminal, so no colour unless the policy is ColourPolicy::Always.
+    fn stream(&self) -> Option<Stream> {
+        None
+    }
+}
+
+/// Allows a sink to be shared with the logger while keeping a handle to it.
+impl<S: Sink + ?Sized> Sink for Arc<S> {
+    fn log(&self, record: &Record, formatted: &str) {
+        (**self).log(record, formatted)
+    }
+
+    fn log_suppressed(&self, record: &Record, formatted: &str) {
+        (**self).log_suppressed(record, formatted)
+    }
+
+    fn level(&self) -> Option<LogLevel> {
+        (**self).level()
+    }
+
+    fn stream(&self) -> Option<Stream> {
+        (**self).stream()
+    }
+}
+
+/// A sink which keeps the records it is sent, for tests of the logging helpers.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct Capture(crate::sync::Mutex<Vec<(String, u32)>>);
+
+#[cfg(test)]
+impl Capture {
+    /// A logger without colour which only writes to the sink.
+    pub(crate) fn logger(self: &Arc<Self>) -> crate::log::Log {
+        crate::log::Log::new_ex()
+            .disable_ansi()
+            .set_sink(Arc::clone(self))
+            .done()
+    }
+
+    /// Each record as formatted, with the line it was logged from.
+    pub(crate) fn records(&self) -> Vec<(String, u32)> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Each record as formatted.
+    pub(crate) fn formatted(&self) -> Vec<String> {
+        self.records()
+            .into_iter()
+            .map(|(formatted, _)| formatted)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Sink for Capture {
+    fn log(&self, record: &Record, formatted: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .push((formatted.to_string(), record.location.line()));
+    }
+}
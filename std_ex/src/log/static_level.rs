@@ -0,0 +1,138 @@
+use crate::log::LogLevel;
+
+/// The feature level for each of the `*_off` through `*_trace` features, in that order.
+const FEATURE_LEVELS: [LogLevel; 7] = [
+    LogLevel::Silent,
+    LogLevel::Fatal,
+    LogLevel::Error,
+    LogLevel::Warn,
+    LogLevel::Info,
+    LogLevel::Debug,
+    LogLevel::Trace,
+];
+
+const MAX_LEVEL: Option<LogLevel> = feature_level([
+    cfg!(feature = "max_level_off"),
+    cfg!(feature = "max_level_fatal"),
+    cfg!(feature = "max_level_error"),
+    cfg!(feature = "max_level_warn"),
+    cfg!(feature = "max_level_info"),
+    cfg!(feature = "max_level_debug"),
+    cfg!(feature = "max_level_trace"),
+]);
+
+const RELEASE_MAX_LEVEL: Option<LogLevel> = feature_level([
+    cfg!(feature = "release_max_level_off"),
+    cfg!(feature = "release_max_level_fatal"),
+    cfg!(feature = "release_max_level_error"),
+    cfg!(feature = "release_max_level_warn"),
+    cfg!(feature = "release_max_level_info"),
+    cfg!(feature = "release_max_level_debug"),
+    cfg!(feature = "release_max_level_trace"),
+]);
+
+/// The most verbose level compiled in. The logging in calls at levels below it is removed,
+/// whatever level is set at runtime, but their arguments are still evaluated, so guard any which
+/// are costly to build:
+///
+/// ```
+/// use std_ex::log::{Log, LogLevel, STATIC_MAX_LEVEL};
+///
+/// let log = Log::new();
+/// if STATIC_MAX_LEVEL <= LogLevel::Debug {
+///     log.debug(format!("state: {:?}", [1, 2, 3]));
+/// }
+/// ```
+///
+/// Set with the cargo features `max_level_off`, `max_level_fatal`, `max_level_error`,
+/// `max_level_warn`, `max_level_info`, `max_level_debug` and `max_level_trace`, and their
+/// `release_max_level_*` forms which only apply to builds without debug assertions and take
+/// precedence. If several are enabled, e.g. by different crates, the least verbose wins.
+/// Default: LogLevel::Trace, so nothing is removed.
+pub const STATIC_MAX_LEVEL: LogLevel =
+    resolve(MAX_LEVEL, RELEASE_MAX_LEVEL, cfg!(debug_assertions));
+
+/// Is the level compiled in.
+/// Tests return early when the level they log at isn't, so the suite passes with any of the
+/// features. scripts/test_static_levels.sh runs it with each of them.
+pub(crate) const fn enabled(level: LogLevel) -> bool {
+    level as u8 >= STATIC_MAX_LEVEL as u8
+}
+
+/// The least verbose level of those whose feature is enabled.
+const fn feature_level(enabled: [bool; 7]) -> Option<LogLevel> {
+    let mut i = 0;
+    while i < enabled.len() {
+        if enabled[i] {
+            return Some(FEATURE_LEVELS[i]);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The release level in builds without debug assertions, otherwise the level, otherwise trace.
+const fn resolve(
+    max_level: Option<LogLevel>,
+    release_max_level: Option<LogLevel>,
+    debug_assertions: bool,
+) -> LogLevel {
+    match (debug_assertions, release_max_level, max_level) {
+        (false, Some(level), _) => level,
+        (_, _, Some(level)) => level,
+        _ => LogLevel::Trace,
+    }
+}
+
+#[cfg(test)]
+mod static_level_tests {
+    use super::*;
+
+    fn features(enabled: &[LogLevel]) -> [bool; 7] {
+        FEATURE_LEVELS.map(|level| enabled.contains(&level))
+    }
+
+    #[test]
+    fn should_pick_least_verbose_enabled_feature() {
+        assert_eq!(feature_level(features(&[])), None);
+        assert_eq!(
+            feature_level(features(&[LogLevel::Info])),
+            Some(LogLevel::Info)
+        );
+        assert_eq!(
+            feature_level(features(&[LogLevel::Debug, LogLevel::Warn])),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(
+            feature_level(features(&[LogLevel::Trace, LogLevel::Silent])),
+            Some(LogLevel::Silent)
+        );
+    }
+
+    #[test]
+    fn should_resolve_each_feature_combination() {
+        let info = Some(LogLevel::Info);
+        let warn = Some(LogLevel::Warn);
+        for debug_assertions in [true, false] {
+            assert_eq!(resolve(None, None, debug_assertions), LogLevel::Trace);
+            assert_eq!(resolve(info, None, debug_assertions), LogLevel::Info);
+        }
+        assert_eq!(resolve(None, warn, true), LogLevel::Trace);
+        assert_eq!(resolve(None, warn, false), LogLevel::Warn);
+        assert_eq!(resolve(info, warn, true), LogLevel::Info);
+        assert_eq!(resolve(info, warn, false), LogLevel::Warn);
+    }
+
+    #[test]
+    fn should_match_enabled_features() {
+        assert_eq!(
+            STATIC_MAX_LEVEL,
+            resolve(MAX_LEVEL, RELEASE_MAX_LEVEL, cfg!(debug_assertions))
+        );
+        assert!(enabled(LogLevel::Silent));
+        assert_eq!(
+            enabled(LogLevel::Trace),
+            STATIC_MAX_LEVEL == LogLevel::Trace
+        );
+    }
+}
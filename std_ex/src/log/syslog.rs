@@ -0,0 +1,458 @@
+use crate::chrono::SecondsFormat;
+use crate::io::{self, Write};
+use crate::log::error_chain;
+use crate::log::{LogLevel, Record, Sink, count_dropped};
+use crate::net::{TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use crate::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use crate::path::Path;
+use crate::sync::Mutex;
+use crate::{env, path::PathBuf, process};
+
+/// The socket local syslog daemons listen on.
+#[cfg(unix)]
+pub const SYSLOG_SOCKET_PATH: &str = "/dev/log";
+
+/// Structured data id used for fields in RFC 5424 messages.
+/// 32473 is the private enterprise number reserved for documentation, as used by RFC 5424.
+const FIELDS_SD_ID: &str = "fields@32473";
+
+/// The syslog message format.
+/// SyslogFormat::Rfc5424 -> `<PRI>1 TIMESTAMP HOST APP PID - [fields@32473 key="value"] MSG`.
+/// SyslogFormat::Rfc3164 -> `<PRI>Mmm dd hh:mm:ss HOST APP[PID]: MSG key=value`, the legacy BSD
+/// format still expected by some daemons.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyslogFormat {
+    #[default]
+    Rfc5424,
+    Rfc3164,
+}
+
+/// The syslog facility, which tells the daemon what kind of program the message is from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User,
+    Mail,
+    Daemon,
+    Auth,
+    Syslog,
+    Lpr,
+    News,
+    Uucp,
+    Cron,
+    AuthPriv,
+    Ftp,
+    Local0 = 16,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+/// A sink which sends each record to a syslog daemon, over a unix datagram socket, UDP or TCP.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std_ex::log::{Facility, Log, SyslogFormat, SyslogSink};
+///
+/// let syslog = SyslogSink::local()
+///     .unwrap()
+///     .set_format(SyslogFormat::Rfc3164)
+///     .set_facility(Facility::Daemon);
+/// let log = Log::new_ex().set_sink(syslog).done();
+/// log.info("sent to /dev/log");
+/// ```
+pub struct SyslogSink {
+    transport: Mutex<Transport>,
+    format: SyslogFormat,
+    facility: Facility,
+    hostname: String,
+    app_name: String,
+    pid: u32,
+}
+
+enum Transport {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl SyslogSink {
+    /// Connect to the local syslog daemon at /dev/log.
+    #[cfg(unix)]
+    pub fn local() -> io::Result<Self> {
+        Self::unix(SYSLOG_SOCKET_PATH)
+    }
+
+    /// Connect to a syslog daemon listening on a unix datagram socket.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::with_transport(Transport::Unix(socket)))
+    }
+
+    /// Send to a syslog daemon over UDP, e.g. `SyslogSink::udp("127.0.0.1:514")`.
+    pub fn udp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+        let bind_addr = match addr.is_ipv4() {
+            true => "0.0.0.0:0",
+            false => "[::]:0",
+        };
+        let socket = UdpSocket::bind(bind_addr)?;
+        socket.connect(addr)?;
+        Ok(Self::with_transport(Transport::Udp(socket)))
+    }
+
+    /// Send to a syslog daemon over TCP, framed using octet counting from RFC 6587.
+    pub fn tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(Self::with_transport(Transport::Tcp(stream)))
+    }
+
+    fn with_transport(transport: Transport) -> Self {
+        Self {
+            transport: Mutex::new(transport),
+            format: SyslogFormat::default(),
+            facility: Facility::default(),
+            hostname: env::hostname().unwrap_or_else(|| String::from("-")),
+            app_name: program_name().unwrap_or_else(|| String::from("-")),
+            pid: process::id(),
+        }
+    }
+
+    /// Set the message format.
+    /// Default: SyslogFormat::Rfc5424.
+    pub fn set_format(mut self, format: SyslogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the facility.
+    /// Default: Facility::User.
+    pub fn set_facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Set the application name, also known as the tag.
+    /// Default: The file name of the running program.
+    pub fn set_app_name<T: Into<String>>(mut self, app_name: T) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Set the host name.
+    /// Default: env::hostname.
+    pub fn set_hostname<T: Into<String>>(mut self, hostname: T) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    /// Format a record as a syslog message.
+    fn format(&self, record: &Record) -> String {
+        let priority = self.facility as u8 * 8 + syslog_severity(record.level);
+        match self.format {
+            SyslogFormat::Rfc5424 => format!(
+                "<{priority}>1 {} {} {} {} - {} {}",
+                record.time.to_rfc3339_opts(SecondsFormat::Micros, false),
+                header_value(&self.hostname, 255),
+                header_value(&self.app_name, 48),
+                self.pid,
+                structured_data(&record.fields),
+                message(record, false),
+            ),
+            SyslogFormat::Rfc3164 => format!(
+                "<{priority}>{} {} {}[{}]: {}",
+                record.time.format("%b %e %H:%M:%S"),
+                header_value(&self.hostname, 255),
+                rfc3164_tag(&self.app_name),
+                self.pid,
+                message(record, true),
+            ),
+        }
+    }
+
+    fn send(&self, message: &str) -> io::Result<()> {
+        let mut transport = self
+            .transport
+            .lock()
+            .map_err(|_| io::Error::other("syslog transport lock poisoned"))?;
+        match &mut *transport {
+            #[cfg(unix)]
+            Transport::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Transport::Tcp(stream) => {
+                write!(stream, "{} {}", message.len(), message)?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+impl Sink for SyslogSink {
+    fn log(&self, record: &Record, _formatted: &str) {
+        // A logger has nowhere to report its own failures, so failed sends are dropped.
+        if self.send(&self.format(record)).is_err() {
+            count_dropped(record.level, &record.target);
+        }
+    }
+}
+
+/// Map a log level onto a syslog severity.
+pub fn syslog_severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Trace | LogLevel::Debug => 7,
+        LogLevel::Info => 6,
+        LogLevel::Warn => 4,
+        LogLevel::Error => 3,
+        LogLevel::Fatal => 2,
+        LogLevel::Silent => 7,
+    }
+}
+
+/// The file name of the running program, used as the default app name.
+pub(crate) fn program_name() -> Option<String> {
+    env::args().next().map(PathBuf::from).and_then(|path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    })
+}
+
+/// RFC 5424 header fields are printable ascii without spaces, limited in length.
+fn header_value(value: &str, max_len: usize) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max_len)
+        .collect();
+    match value.is_empty() {
+        true => String::from("-"),
+        false => value,
+    }
+}
+
+/// RFC 3164 tags are printable ascii up to 32 characters, and end at a `[` or `:`.
+fn rfc3164_tag(app_name: &str) -> String {
+    let tag: String = app_name
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !matches!(c, ':' | '[' | ']'))
+        .take(32)
+        .collect();
+    match tag.is_empty() {
+        true => String::from("-"),
+        false => tag,
+    }
+}
+
+/// Fields as RFC 5424 SD-PARAMs, whose names are 1 to 32 printable ascii characters, so a name
+/// with none left is written as `_`.
+fn structured_data(fields: &[(String, String)]) -> String {
+    if fields.is_empty() {
+        return String::from("-");
+    }
+
+    let mut data = format!("[{FIELDS_SD_ID}");
+    for (key, value) in fields {
+        let name: String = key
+            .chars()
+            .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+            .take(32)
+            .collect();
+        let name = match name.is_empty() {
+            true => "_",
+            false => &name,
+        };
+        let value = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace(']', "\\]");
+        data.push_str(&format!(" {name}=\"{value}\""));
+    }
+    data.push(']');
+    data
+}
+
+/// The free form message, led by any target and prefix, with data, error chain, backtrace and
+/// context on the following lines.
+/// RFC 3164 has no structured data, so fields are written as `key=value` pairs.
+pub(crate) fn message(record: &Record, with_fields: bool) -> String {
+    let mut message = match record.prefix.trim() {
+        "" => record.msg.clone(),
+        prefix => format!("{prefix} {}", record.msg),
+    };
+    if !record.target.is_empty() {
+        message = format!("{}: {message}", record.target);
+    }
+    if with_fields {
+        for (key, value) in &record.fields {
+            message.push_str(&format!(" {key}={value}"));
+        }
+    }
+    if let Some(data) = &record.data {
+        message.push('\n');
+        message.push_str(data);
+    }
+    if !record.errors.is_empty() {
+        message.push('\n');
+        message.push_str(&error_chain::text(&record.errors));
+    }
+    if let Some(backtrace) = &record.backtrace {
+        message.push_str("\nBacktrace:\n");
+        message.push_str(backtrace);
+    }
+    if !record.context.is_empty() {
+        message.push_str("\nContext:\n");
+        message.push_str(&record.context);
+    }
+    message
+}
+
+#[cfg(test)]
+mod syslog_tests {
+    use super::*;
+    use crate::chrono::{self, TimeZone};
+    use crate::io::Read;
+    use crate::log::network::test_sockets::{TIMEOUT, accept, udp_server};
+    use crate::log::{Log, static_level};
+    use crate::net::TcpListener;
+
+    fn record() -> Record {
+        Record {
+            time: chrono::Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            prefix: String::from(" db:"),
+            fields: vec![(String::from("query"), String::from("select \"a\"]"))],
+            ..Record::test(LogLevel::Warn, "slow query")
+        }
+    }
+
+    fn udp_pair() -> (UdpSocket, SyslogSink) {
+        let server = udp_server();
+        let sink = SyslogSink::udp(server.local_addr().unwrap())
+            .unwrap()
+            .set_hostname("host")
+            .set_app_name("app");
+        (server, sink)
+    }
+
+    #[test]
+    fn should_map_levels_to_severities() {
+        assert_eq!(syslog_severity(LogLevel::Debug), 7);
+        assert_eq!(syslog_severity(LogLevel::Info), 6);
+        assert_eq!(syslog_severity(LogLevel::Warn), 4);
+        assert_eq!(syslog_severity(LogLevel::Error), 3);
+        assert_eq!(syslog_severity(LogLevel::Fatal), 2);
+    }
+
+    #[test]
+    fn should_format_rfc5424_with_structured_data() {
+        let (_, sink) = udp_pair();
+        let sink = sink.set_facility(Facility::Local0);
+        let record = record();
+        let timestamp = record.time.to_rfc3339_opts(SecondsFormat::Micros, false);
+        assert_eq!(
+            sink.format(&record),
+            format!(
+                r#"<132>1 {timestamp} host app {} - [fields@32473 query="select \"a\"\]"] db: slow query"#,
+                process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn should_format_rfc3164() {
+        let (_, sink) = udp_pair();
+        let sink = sink.set_format(SyslogFormat::Rfc3164);
+        assert_eq!(
+            sink.format(&record()),
+            format!(
+                r#"<12>Jan  2 03:04:05 host app[{}]: db: slow query query=select "a"]"#,
+                process::id()
+            )
+        );
+    }
+
+    #[test]
+    fn should_sanitise_header_fields_and_empty_param_names() {
+        let (_, sink) = udp_pair();
+        let mut record = record();
+        record.fields = vec![(String::from(" é "), String::from("v"))];
+        let rfc5424 = sink.set_hostname("my host").set_app_name("my app: x");
+        assert!(rfc5424.format(&record).contains(" myhost myapp:x "));
+        assert!(rfc5424.format(&record).contains(r#"[fields@32473 _="v"]"#));
+        let rfc3164 = rfc5424.set_format(SyslogFormat::Rfc3164);
+        assert!(
+            rfc3164
+                .format(&record)
+                .contains(&format!(" myhost myappx[{}]: ", process::id()))
+        );
+    }
+
+    #[test]
+    fn should_send_over_udp() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let (server, sink) = udp_pair();
+        let log = Log::new_ex().set_sink(sink).done();
+        log.error("over udp");
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..len]);
+        assert!(message.starts_with("<11>1 "));
+        assert!(message.ends_with(" - - over udp"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_send_over_unix_datagram() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let path = env::temp_dir().join(format!("std_ex_syslog_{}.sock", process::id()));
+        let _ = crate::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        server.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let sink = SyslogSink::unix(&path)
+            .unwrap()
+            .set_format(SyslogFormat::Rfc3164);
+        let log = Log::new_ex().set_sink(sink).done();
+        log.error("over unix");
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let _ = crate::fs::remove_file(&path);
+        assert!(String::from_utf8_lossy(&buf[..len]).ends_with("]: over unix"));
+    }
+
+    #[test]
+    fn should_frame_messages_over_tcp() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let sink = SyslogSink::tcp(listener.local_addr().unwrap()).unwrap();
+        let mut server = accept(&listener);
+        let log = Log::new_ex().set_sink(sink).done();
+        log.error("over tcp");
+        drop(log);
+
+        let mut received = String::new();
+        server.read_to_string(&mut received).unwrap();
+        let (len, message) = received.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), message.len());
+        assert!(message.ends_with(" - - over tcp"));
+    }
+}
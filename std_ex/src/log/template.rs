@@ -0,0 +1,517 @@
+use crate::borrow::Cow;
+use crate::iter::Peekable;
+use crate::log::error_chain;
+use crate::log::{Record, Timestamp};
+use crate::str::{Chars, FromStr};
+use crate::{error::Error, fmt, fmt::Display, term};
+
+/// The layout used when no template is configured.
+/// Renders as `[time] LEVEL: target: prefix msg key=value` followed by any data, error chain,
+/// backtrace and context on the next lines.
+pub const DEFAULT_TEMPLATE: &str = concat!(
+    "{?[{time}] }{level}:{? {target}:}{? {prefix}} {msg}{? {fields}}",
+    "{?\n{data}}{?\n{errors}}",
+    "{?\nBacktrace:\n{backtrace}}{?\nContext:\n{context}}",
+);
+
+/// A text layout for log records, parsed once and rendered for every log.
+///
+/// Placeholders are written as `{name}` or `{name:spec}` where name is one of `time`, `level`,
+/// `target`, `prefix`, `msg`, `fields`, `data`, `errors`, `backtrace` or `context`, and spec
+/// follows std::fmt's `[[fill]align][width][.precision]` syntax, e.g. `{level:>5}` or
+/// `{prefix:-^20.10}`. Padding is applied to the visible text, so a coloured level lines up the
+/// same as an uncoloured one.
+///
+/// Text wrapped in `{?` and `}` is an optional group, which is only rendered when every
+/// placeholder inside it is non-empty. This is how separators are kept out of the output when
+/// the part they separate is missing, e.g. `{? ({prefix})}`. Groups cannot be nested.
+///
+/// Literal braces are written as `{{` and `}}`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::log::{Log, Template};
+///
+/// let template: Template = "{?{time} }[{level:>5}]{? {prefix}:} {msg}".parse().unwrap();
+/// let log = Log::new_ex().set_template(template).done();
+/// log.info("hello");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTemplateError {
+    reason: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Part, Spec),
+    Optional(Vec<Segment>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Part {
+    Time,
+    Level,
+    Target,
+    Prefix,
+    Msg,
+    Fields,
+    Data,
+    Errors,
+    Backtrace,
+    Context,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Centre,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Spec {
+    fill: char,
+    align: Align,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            fill: ' ',
+            align: Align::Left,
+            width: 0,
+            precision: None,
+        }
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        DEFAULT_TEMPLATE
+            .parse()
+            .expect("default log template should be valid")
+    }
+}
+
+impl FromStr for Template {
+    type Err = ParseTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars().peekable();
+        let segments = parse_segments(&mut chars, false)?;
+        Ok(Template { segments })
+    }
+}
+
+impl ParseTemplateError {
+    fn new<T: Into<String>>(reason: T) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ParseTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid log template: {}", self.reason)
+    }
+}
+
+impl Error for ParseTemplateError {}
+
+impl FromStr for Part {
+    type Err = ParseTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let part = match s {
+            "time" => Part::Time,
+            "level" => Part::Level,
+            "target" => Part::Target,
+            "prefix" => Part::Prefix,
+            "msg" => Part::Msg,
+            "fields" => Part::Fields,
+            "data" => Part::Data,
+            "errors" => Part::Errors,
+            "backtrace" => Part::Backtrace,
+            "context" => Part::Context,
+            _ => {
+                return Err(ParseTemplateError::new(format!(
+                    "unknown placeholder `{s}`"
+                )));
+            }
+        };
+
+        Ok(part)
+    }
+}
+
+impl FromStr for Spec {
+    type Err = ParseTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut spec = Spec::default();
+        let chars: Vec<char> = s.chars().collect();
+        let mut i = 0;
+
+        if chars.len() >= 2 && as_align(chars[1]).is_some() {
+            spec.fill = chars[0];
+            spec.align = as_align(chars[1]).unwrap_or(Align::Left);
+            i = 2;
+        } else if let Some(align) = chars.first().copied().and_then(as_align) {
+            spec.align = align;
+            i = 1;
+        }
+
+        let width: String = chars[i..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        i += width.len();
+        if !width.is_empty() {
+            spec.width = parse_number(&width, s)?;
+        }
+
+        if chars.get(i) == Some(&'.') {
+            let precision: String = chars[i + 1..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            if precision.is_empty() {
+                return Err(ParseTemplateError::new(format!(
+                    "missing precision in spec `{s}`"
+                )));
+            }
+            i += precision.len() + 1;
+            spec.precision = Some(parse_number(&precision, s)?);
+        }
+
+        if i != chars.len() {
+            return Err(ParseTemplateError::new(format!("invalid spec `{s}`")));
+        }
+
+        Ok(spec)
+    }
+}
+
+fn as_align(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Centre),
+        '>' => Some(Align::Right),
+        _ => None,
+    }
+}
+
+fn parse_number(digits: &str, spec: &str) -> Result<usize, ParseTemplateError> {
+    digits
+        .parse()
+        .map_err(|_| ParseTemplateError::new(format!("number out of range in spec `{spec}`")))
+}
+
+/// Parse literals, placeholders and groups until the end of the input, or the end of the group
+/// when parsing inside one.
+fn parse_segments(
+    chars: &mut Peekable<Chars>,
+    in_group: bool,
+) -> Result<Vec<Segment>, ParseTemplateError> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+
+    loop {
+        let Some(c) = chars.next() else {
+            if in_group {
+                return Err(ParseTemplateError::new("unclosed `{?` group"));
+            }
+            break;
+        };
+
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '}' if in_group => break,
+            '}' => {
+                return Err(ParseTemplateError::new(
+                    "unmatched `}`, use `}}` for a literal",
+                ));
+            }
+            '{' if chars.peek() == Some(&'?') => {
+                if in_group {
+                    return Err(ParseTemplateError::new("`{?` groups cannot be nested"));
+                }
+                chars.next();
+                flush_literal(&mut literal, &mut segments);
+                segments.push(Segment::Optional(parse_segments(chars, true)?));
+            }
+            '{' => {
+                flush_literal(&mut literal, &mut segments);
+                segments.push(parse_placeholder(chars)?);
+            }
+            c => literal.push(c),
+        }
+    }
+
+    flush_literal(&mut literal, &mut segments);
+    Ok(segments)
+}
+
+fn flush_literal(literal: &mut String, segments: &mut Vec<Segment>) {
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(crate::mem::take(literal)));
+    }
+}
+
+/// Parse the body of a `{name:spec}` placeholder, after the opening brace.
+fn parse_placeholder(chars: &mut Peekable<Chars>) -> Result<Segment, ParseTemplateError> {
+    let mut body = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => body.push(c),
+            None => return Err(ParseTemplateError::new("unclosed placeholder")),
+        }
+    }
+
+    let (name, spec) = match body.split_once(':') {
+        Some((name, spec)) => (name, spec.parse()?),
+        None => (body.as_str(), Spec::default()),
+    };
+
+    Ok(Segment::Placeholder(name.trim().parse()?, spec))
+}
+
+impl Template {
+    /// Render a record using this template.
+    pub(crate) fn render(&self, record: &Record, timestamp: &Timestamp, ansi: bool) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Optional(segments) => {
+                    if let Some(group) = render_group(segments, record, timestamp, ansi) {
+                        output.push_str(&group);
+                    }
+                }
+                segment => {
+                    render_segment(segment, record, timestamp, ansi, &mut output);
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Render a group, returning None if any placeholder inside it is empty.
+fn render_group(
+    segments: &[Segment],
+    record: &Record,
+    timestamp: &Timestamp,
+    ansi: bool,
+) -> Option<String> {
+    let mut output = String::new();
+    for segment in segments {
+        if !render_segment(segment, record, timestamp, ansi, &mut output) {
+            return None;
+        }
+    }
+    Some(output)
+}
+
+/// Render a single literal or placeholder, returning false if it was an empty placeholder.
+fn render_segment(
+    segment: &Segment,
+    record: &Record,
+    timestamp: &Timestamp,
+    ansi: bool,
+    output: &mut String,
+) -> bool {
+    let (part, spec) = match segment {
+        Segment::Literal(literal) => {
+            output.push_str(literal);
+            return true;
+        }
+        Segment::Placeholder(part, spec) => (*part, spec),
+        Segment::Optional(_) => return true,
+    };
+
+    let value = part_value(part, record, timestamp);
+    if value.is_empty() {
+        return false;
+    }
+
+    let value = apply_spec(spec, &value);
+    match part {
+        Part::Level if ansi => {
+            output.push_str(&term::Colour::from(record.level).paint(value));
+        }
+        _ => output.push_str(&value),
+    }
+    true
+}
+
+fn part_value<'a>(part: Part, record: &'a Record, timestamp: &Timestamp) -> Cow<'a, str> {
+    match part {
+        Part::Time => Cow::Owned(timestamp.format(&record.time).unwrap_or_default()),
+        Part::Level => Cow::Owned(record.level.to_string().to_uppercase()),
+        Part::Target => Cow::Borrowed(&record.target),
+        Part::Prefix => Cow::Borrowed(record.prefix.trim()),
+        Part::Msg => Cow::Borrowed(&record.msg),
+        Part::Fields => Cow::Owned(fields_value(&record.fields)),
+        Part::Data => Cow::Borrowed(record.data.as_deref().unwrap_or("")),
+        Part::Errors => Cow::Owned(error_chain::text(&record.errors)),
+        Part::Backtrace => Cow::Borrowed(record.backtrace.as_deref().unwrap_or("")),
+        Part::Context => Cow::Borrowed(&record.context),
+    }
+}
+
+/// Render fields as space separated `key=value` pairs, quoting values which need it.
+fn fields_value(fields: &[(String, String)]) -> String {
+    let pairs: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| {
+            let needs_quotes = value.is_empty()
+                || value
+                    .chars()
+                    .any(|c| c.is_whitespace() || c == '=' || c == '"');
+            match needs_quotes {
+                true => format!("{key}={value:?}"),
+                false => format!("{key}={value}"),
+            }
+        })
+        .collect();
+    pairs.join(" ")
+}
+
+fn apply_spec(spec: &Spec, value: &str) -> String {
+    let value: String = match spec.precision {
+        Some(precision) => value.chars().take(precision).collect(),
+        None => value.to_string(),
+    };
+
+    let len = value.chars().count();
+    if len >= spec.width {
+        return value;
+    }
+
+    let padding = spec.width - len;
+    let (before, after) = match spec.align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Centre => (padding / 2, padding - padding / 2),
+    };
+    let fill = String::from(spec.fill);
+    format!("{}{}{}", fill.repeat(before), value, fill.repeat(after))
+}
+
+#[cfg(test)]
+mod template_tests {
+    use super::*;
+    use crate::log::LogLevel;
+
+    fn record() -> Record {
+        Record {
+            prefix: String::from(" first: second:"),
+            ..Record::test(LogLevel::Info, "message")
+        }
+    }
+
+    fn render(template: &str, record: &Record) -> String {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(record, &Timestamp::None, false)
+    }
+
+    #[test]
+    fn default_template_should_not_leave_trailing_whitespace() {
+        let mut record = record();
+        record.prefix = String::new();
+        assert_eq!(render(DEFAULT_TEMPLATE, &record), "INFO: message");
+    }
+
+    #[test]
+    fn default_template_should_render_data_and_context_on_new_lines() {
+        let mut record = record();
+        record.data = Some(String::from("[1, 2]"));
+        record.context = String::from("ctx");
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &record),
+            "INFO: first: second: message\n[1, 2]\nContext:\nctx"
+        );
+    }
+
+    #[test]
+    fn default_template_should_render_target_before_prefix() {
+        let mut record = record();
+        record.target = String::from("app.db");
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &record),
+            "INFO: app.db: first: second: message"
+        );
+    }
+
+    #[test]
+    fn should_render_fields_as_key_value_pairs() {
+        let mut record = record();
+        record.fields = vec![
+            (String::from("requestId"), String::from("abc")),
+            (String::from("user"), String::from("a name")),
+        ];
+        assert_eq!(
+            render("{msg}{? {fields}}", &record),
+            r#"message requestId=abc user="a name""#
+        );
+    }
+
+    #[test]
+    fn should_pad_and_align_placeholders() {
+        let record = record();
+        assert_eq!(render("[{level:>5}]", &record), "[ INFO]");
+        assert_eq!(render("[{level:<6}]", &record), "[INFO  ]");
+        assert_eq!(render("[{level:*^8}]", &record), "[**INFO**]");
+        assert_eq!(render("[{msg:.3}]", &record), "[mes]");
+    }
+
+    #[test]
+    fn should_pad_level_outside_of_colour_codes() {
+        let template: Template = "{level:>5}".parse().unwrap();
+        let rendered = template.render(&record(), &Timestamp::None, true);
+        assert_eq!(rendered, term::Colour::Blue.paint(" INFO"));
+    }
+
+    #[test]
+    fn should_skip_group_when_a_placeholder_is_empty() {
+        let mut record = record();
+        assert_eq!(render("{msg}{? ({context})}", &record), "message");
+        record.context = String::from("ctx");
+        assert_eq!(render("{msg}{? ({context})}", &record), "message (ctx)");
+    }
+
+    #[test]
+    fn should_unescape_literal_braces() {
+        assert_eq!(render("{{{msg}}}", &record()), "{message}");
+    }
+
+    #[test]
+    fn should_reject_invalid_templates() {
+        assert!("{unknown}".parse::<Template>().is_err());
+        assert!("{msg".parse::<Template>().is_err());
+        assert!("{? {msg}".parse::<Template>().is_err());
+        assert!("{?{?{msg}}}".parse::<Template>().is_err());
+        assert!("{msg:>x}".parse::<Template>().is_err());
+        assert!("msg}".parse::<Template>().is_err());
+    }
+}
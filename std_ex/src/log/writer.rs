@@ -0,0 +1,150 @@
+use crate::io::{self, Write};
+use crate::log::{Log, LogLevel};
+use crate::panic::Location;
+
+/// An io::Write adapter which turns a byte stream into logs, created by Log::writer.
+///
+/// Each complete line written becomes one log at the writer's level, with CRLF line endings
+/// trimmed, invalid UTF-8 replaced and empty lines skipped. A partial line is kept until the
+/// rest of it is written, or logged on its own when the writer is dropped. flush does not log
+/// partial lines, so wrapping the writer in a BufWriter doesn't split them.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::Write;
+/// use std_ex::log::{Log, LogLevel};
+///
+/// let log = Log::new();
+/// let mut writer = log.writer(LogLevel::Info);
+/// writeln!(writer, "one log").unwrap();
+/// write!(writer, "another ").unwrap();
+/// write!(writer, "log").unwrap();
+/// drop(writer);
+/// ```
+pub struct LogWriter {
+    log: Log,
+    level: LogLevel,
+    location: &'static Location<'static>,
+    buffer: Vec<u8>,
+}
+
+impl LogWriter {
+    pub(crate) fn new(log: Log, level: LogLevel, location: &'static Location<'static>) -> Self {
+        Self {
+            log,
+            level,
+            location,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn log_line(&self, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            return;
+        }
+        let line = String::from_utf8_lossy(line);
+        self.log.logger_at(self.level, &line, None, self.location);
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if let Some(end) = self.buffer.iter().rposition(|byte| *byte == b'\n') {
+            let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+            for line in lines[..end].split(|byte| *byte == b'\n') {
+                self.log_line(line);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        if !self.buffer.is_empty() {
+            let tail = crate::mem::take(&mut self.buffer);
+            self.log_line(&tail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use super::*;
+    use crate::log::static_level;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static OUTPUT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn capture(log: String) {
+        OUTPUT.with(|output| output.borrow_mut().push(log));
+    }
+
+    fn take_output() -> Vec<String> {
+        OUTPUT.with(|output| output.take())
+    }
+
+    fn writer() -> LogWriter {
+        Log::new_ex()
+            .disable_ansi()
+            .set_logging_function(capture)
+            .done()
+            .writer(LogLevel::Error)
+    }
+
+    #[test]
+    fn should_log_each_complete_line() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut writer = writer();
+        writer.write_all(b"first\nsecond\r\n\nthi").unwrap();
+        assert_eq!(take_output(), vec!["ERROR: first", "ERROR: second"]);
+        writer.write_all(b"rd\n").unwrap();
+        assert_eq!(take_output(), vec!["ERROR: third"]);
+    }
+
+    #[test]
+    fn should_keep_partial_lines_on_flush_and_log_them_on_drop() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut writer = writer();
+        writer.write_all(b"partial").unwrap();
+        writer.flush().unwrap();
+        assert!(take_output().is_empty());
+        drop(writer);
+        assert_eq!(take_output(), vec!["ERROR: partial"]);
+    }
+
+    #[test]
+    fn should_replace_invalid_utf8() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut writer = writer();
+        writer.write_all(b"bad \xFF byte\n").unwrap();
+        assert_eq!(take_output(), vec!["ERROR: bad \u{FFFD} byte"]);
+    }
+
+    #[test]
+    fn should_not_split_multi_byte_characters_across_writes() {
+        if !static_level::enabled(LogLevel::Error) {
+            return;
+        }
+        let mut writer = writer();
+        let bytes = "é\n".as_bytes();
+        writer.write_all(&bytes[..1]).unwrap();
+        writer.write_all(&bytes[1..]).unwrap();
+        assert_eq!(take_output(), vec!["ERROR: é"]);
+    }
+}
@@ -1,7 +1,12 @@
 use crate::fmt::Display;
+pub(crate) use crate::term::ansi::sequence_len;
+pub use crate::term::ansi::strip_ansi;
 pub use crate::term::border::Border;
+pub use crate::term::colour_policy::{ColourPolicy, Stream};
 
+mod ansi;
 mod border;
+mod colour_policy;
 
 type Ansi = &'static str;
 
@@ -9,7 +14,7 @@ const CLEAR_TERMINAL: Ansi = "\x1B[2J";
 const CLEAR_LINE: Ansi = "\x1B[2K";
 const BOLD_ON: Ansi = "\x1B[1m";
 const BOLD_OFF: Ansi = "\x1B[22m";
-const RESET: Ansi = "\x1B[0m";
+pub(crate) const RESET: Ansi = "\x1B[0m";
 const UNDERLINE_ON: Ansi = "\x1B[4m";
 const UNDERLINE_OFF: Ansi = "\x1B[24m";
 const OVERLINE_ON: Ansi = "\x1B[53m";
@@ -64,30 +69,30 @@ pub struct CustomColour {
 }
 
 impl Colour {
-    pub fn paint_with<T: AsRef<str>>(colour: Colour, s: T) -> String
+    pub fn paint_with<T>(colour: Colour, s: T) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         format!("{}{}{}", colour, s, Colour::Off)
     }
 
-    pub fn paint<T: AsRef<str>>(&self, s: T) -> String
+    pub fn paint<T>(&self, s: T) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         format!("{}{}{}", &self, s, Colour::Off)
     }
 
-    pub fn paint_bg_with<T: AsRef<str>>(colour: Colour, s: T) -> String
+    pub fn paint_bg_with<T>(colour: Colour, s: T) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         format!("{}{}{}", colour.as_bg_str(), s, Colour::Off.as_bg_str())
     }
 
-    pub fn paint_bg<T: AsRef<str>>(&self, s: T) -> String
+    pub fn paint_bg<T>(&self, s: T) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         format!("{}{}{}", &self.as_bg_str(), s, Colour::Off.as_bg_str())
     }
@@ -135,16 +140,16 @@ impl Colour {
 }
 
 impl CustomColour {
-    pub fn paint<T: AsRef<str>>(&self, s: T) -> String
+    pub fn paint<T>(&self, s: T) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         format!("{}{}{}", self, s, Colour::Off)
     }
 
-    pub fn paint_bg<T: AsRef<str>>(&self, s: T) -> String
+    pub fn paint_bg<T>(&self, s: T) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         format!("{}{}{}", self.bg_value, s, Colour::Off.as_bg_str())
     }
@@ -198,6 +203,7 @@ pub struct PrinterBuilder {
     underline_prefix: &'static str,
     underline_suffix: &'static str,
     border: Border,
+    colour_policy: ColourPolicy,
 }
 
 pub struct Printer {
@@ -205,6 +211,7 @@ pub struct Printer {
 }
 
 impl Printer {
+    #[allow(clippy::new_ret_no_self)]
     pub const fn new() -> PrinterBuilder {
         PrinterBuilder {
             bold_prefix: "",
@@ -216,35 +223,36 @@ impl Printer {
             underline_prefix: "",
             underline_suffix: "",
             border: Border::None,
+            colour_policy: ColourPolicy::Auto,
         }
     }
 
-    pub fn print<T: AsRef<str>>(&self, str: T)
+    pub fn print<T>(&self, str: T)
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
-        print!("{}", self.format(str))
+        print!("{}", self.format(str, Stream::Stdout))
     }
 
-    pub fn println<T: AsRef<str>>(&self, str: T)
+    pub fn println<T>(&self, str: T)
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
-        println!("{}", self.format(str))
+        println!("{}", self.format(str, Stream::Stdout))
     }
 
-    pub fn eprint<T: AsRef<str>>(&self, str: T)
+    pub fn eprint<T>(&self, str: T)
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
-        print!("{}", self.format(str))
+        eprint!("{}", self.format(str, Stream::Stderr))
     }
 
-    pub fn eprintln<T: AsRef<str>>(&self, str: T)
+    pub fn eprintln<T>(&self, str: T)
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
-        eprintln!("{}", self.format(str))
+        eprintln!("{}", self.format(str, Stream::Stderr))
     }
 
     pub fn clear(&self) {
@@ -255,15 +263,19 @@ impl Printer {
         clear_line();
     }
 
-    fn format<T: AsRef<str>>(&self, str: T) -> String
+    fn format<T>(&self, str: T, stream: Stream) -> String
     where
-        T: Display,
+        T: AsRef<str> + Display,
     {
         let (border_top, border_prefix, border_suffix, border_bottom) = self
             .config
             .border
             .generate_border_components(str.as_ref().len());
 
+        if !self.config.colour_policy.should_colour(stream) {
+            return format!("{border_top}{border_prefix}{str}{border_suffix}{border_bottom}");
+        }
+
         format!(
             "{}{}{}{}{}{}{str}{}{}{}{}{}{}",
             border_top,
@@ -312,6 +324,13 @@ impl PrinterBuilder {
         self
     }
 
+    /// Set when ansi codes are written.
+    /// Default: ColourPolicy::Auto, resolved against the stream being printed to.
+    pub const fn set_colour_policy(mut self, colour_policy: ColourPolicy) -> Self {
+        self.colour_policy = colour_policy;
+        self
+    }
+
     pub const fn done(self) -> Printer {
         Printer { config: self }
     }
@@ -0,0 +1,157 @@
+use crate::borrow::Cow;
+
+const ESC: char = '\x1B';
+const BEL: char = '\x07';
+const C1_DCS: char = '\u{90}';
+const C1_SOS: char = '\u{98}';
+const C1_CSI: char = '\u{9B}';
+const C1_ST: char = '\u{9C}';
+const C1_OSC: char = '\u{9D}';
+const C1_PM: char = '\u{9E}';
+const C1_APC: char = '\u{9F}';
+
+/// Removes ansi escape sequences from a string, leaving only the visible text.
+///
+/// Handles CSI sequences (colours, cursor movement), OSC sequences (titles, hyperlinks)
+/// terminated by BEL or ST, DCS/SOS/PM/APC strings, short escape sequences such as charset
+/// selection, and their 8-bit C1 equivalents. Unterminated sequences are stripped to the end
+/// of the string, so a truncated code can't leak through.
+///
+/// # Examples
+///
+/// ```rust
+/// use std_ex::term::{self, Colour};
+///
+/// let painted = Colour::Red.paint("error");
+/// assert_eq!(term::strip_ansi(&painted), "error");
+/// ```
+pub fn strip_ansi(s: &str) -> Cow<'_, str> {
+    if !s.contains(is_sequence_start) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut output = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find(is_sequence_start) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = sequence_len(rest).unwrap_or(rest.len());
+        rest = &rest[len..];
+    }
+    output.push_str(rest);
+
+    Cow::Owned(output)
+}
+
+/// Length in bytes of the escape sequence at the start of the string, None if it doesn't start
+/// with one. Unterminated sequences extend to the end of the string.
+pub(crate) fn sequence_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    let (_, first) = chars.next()?;
+
+    let kind = match first {
+        ESC => match chars.next() {
+            None => return Some(first.len_utf8()),
+            Some((_, '[')) => Kind::Csi,
+            Some((_, ']')) => Kind::Osc,
+            Some((_, 'P' | 'X' | '^' | '_')) => Kind::String,
+            Some((_, '\x20'..='\x2F')) => Kind::Escape,
+            Some((i, c @ '\x30'..='\x7E')) => return Some(i + c.len_utf8()),
+            Some((i, _)) => return Some(i),
+        },
+        C1_CSI => Kind::Csi,
+        C1_OSC => Kind::Osc,
+        C1_DCS | C1_SOS | C1_PM | C1_APC => Kind::String,
+        _ => return None,
+    };
+
+    let mut previous = first;
+    for (i, c) in chars {
+        let end = i + c.len_utf8();
+        match kind {
+            Kind::Csi => match c {
+                '\x20'..='\x3F' => (),
+                '\x40'..='\x7E' => return Some(end),
+                // malformed, end the sequence before the offending character
+                _ => return Some(i),
+            },
+            Kind::Osc | Kind::String => {
+                if c == C1_ST || (c == BEL && kind == Kind::Osc) {
+                    return Some(end);
+                }
+                if c == '\\' && previous == ESC {
+                    return Some(end);
+                }
+            }
+            Kind::Escape => match c {
+                '\x20'..='\x2F' => (),
+                '\x30'..='\x7E' => return Some(end),
+                _ => return Some(i),
+            },
+        }
+        previous = c;
+    }
+
+    Some(s.len())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Csi,
+    Osc,
+    String,
+    Escape,
+}
+
+fn is_sequence_start(c: char) -> bool {
+    matches!(c, ESC | C1_CSI | C1_OSC | C1_DCS | C1_SOS | C1_PM | C1_APC)
+}
+
+#[cfg(test)]
+mod strip_ansi_tests {
+    use super::*;
+    use crate::term::{Colour, bold};
+
+    #[test]
+    fn should_borrow_strings_without_escapes() {
+        assert!(matches!(strip_ansi("plain text"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn should_strip_colours_and_formatting() {
+        let painted = format!(
+            "{} and {}",
+            Colour::Green.paint("green"),
+            bold(String::from("bold"))
+        );
+        assert_eq!(strip_ansi(&painted), "green and bold");
+        assert_eq!(strip_ansi(&Colour::custom(1, 2, 3).paint("rgb")), "rgb");
+    }
+
+    #[test]
+    fn should_strip_osc_with_either_terminator() {
+        let link = "\x1B]8;;https://example.com\x1B\\link\x1B]8;;\x1B\\";
+        assert_eq!(strip_ansi(link), "link");
+        assert_eq!(strip_ansi("\x1B]0;title\x07text"), "text");
+    }
+
+    #[test]
+    fn should_strip_short_and_c1_sequences() {
+        assert_eq!(strip_ansi("\x1B(Bcharset\x1B7"), "charset");
+        assert_eq!(strip_ansi("\u{9B}31mred\u{9B}0m"), "red");
+        assert_eq!(strip_ansi("\x1BPdevice\x1B\\control"), "control");
+    }
+
+    #[test]
+    fn should_strip_unterminated_sequences_to_the_end() {
+        assert_eq!(strip_ansi("text\x1B[31"), "text");
+        assert_eq!(strip_ansi("text\x1B]0;title"), "text");
+        assert_eq!(strip_ansi("text\x1B"), "text");
+    }
+
+    #[test]
+    fn should_keep_text_after_malformed_csi() {
+        assert_eq!(strip_ansi("\x1B[31\nnext"), "\nnext");
+        assert_eq!(strip_ansi("\x1B[3é"), "é");
+    }
+}
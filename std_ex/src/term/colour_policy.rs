@@ -0,0 +1,161 @@
+use crate::env;
+use crate::io::{self, IsTerminal};
+
+const NO_COLOR: &str = "NO_COLOR";
+const FORCE_COLOR: &str = "FORCE_COLOR";
+const CLICOLOR: &str = "CLICOLOR";
+const CLICOLOR_FORCE: &str = "CLICOLOR_FORCE";
+const TERM: &str = "TERM";
+const AWS_LAMBDA_ENV_VAR_NAME: &str = "AWS_LAMBDA_FUNCTION_NAME";
+
+/// CI providers whose log viewers render ansi colour codes, even though output isn't a terminal.
+const CI_ENV_VAR_NAMES: [&str; 8] = [
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "DRONE",
+    "APPVEYOR",
+    "TEAMCITY_VERSION",
+];
+
+/// Decides whether ansi colour codes should be written.
+///
+/// ColourPolicy::Auto -> Colour when the stream is a terminal, or a CI log viewer which renders
+/// colour, honouring NO_COLOR, FORCE_COLOR, CLICOLOR, CLICOLOR_FORCE and TERM=dumb. Colour is
+/// always disabled inside aws lambda.
+/// ColourPolicy::Always -> Always write colour codes.
+/// ColourPolicy::Never -> Never write colour codes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColourPolicy {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The standard stream output is written to, used to resolve ColourPolicy::Auto.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    /// Is the stream attached to a terminal.
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Stream::Stdout => io::stdout().is_terminal(),
+            Stream::Stderr => io::stderr().is_terminal(),
+        }
+    }
+}
+
+impl ColourPolicy {
+    /// Should colour codes be written to the given stream under this policy.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std_ex::term::{ColourPolicy, Stream};
+    ///
+    /// assert!(!ColourPolicy::Never.should_colour(Stream::Stdout));
+    /// assert!(ColourPolicy::Always.should_colour(Stream::Stdout));
+    /// ```
+    pub fn should_colour(&self, stream: Stream) -> bool {
+        self.resolve(stream.is_terminal(), |key| env::var(key).ok())
+    }
+
+    fn resolve<F>(&self, is_terminal: bool, var: F) -> bool
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        match self {
+            ColourPolicy::Always => return true,
+            ColourPolicy::Never => return false,
+            ColourPolicy::Auto => (),
+        }
+
+        if var(NO_COLOR).is_some_and(|value| !value.is_empty()) {
+            return false;
+        }
+        if let Some(value) = var(FORCE_COLOR) {
+            return !matches!(value.as_str(), "0" | "false");
+        }
+        if var(CLICOLOR_FORCE).is_some_and(|value| !value.is_empty() && value != "0") {
+            return true;
+        }
+        if var(AWS_LAMBDA_ENV_VAR_NAME).is_some() {
+            return false;
+        }
+        if var(TERM).is_some_and(|value| value == "dumb") {
+            return false;
+        }
+        if var(CLICOLOR).is_some_and(|value| value == "0") {
+            return false;
+        }
+
+        is_terminal || CI_ENV_VAR_NAMES.iter().any(|key| var(key).is_some())
+    }
+}
+
+#[cfg(test)]
+mod colour_policy_tests {
+    use super::*;
+
+    fn resolve(policy: ColourPolicy, is_terminal: bool, vars: &[(&str, &str)]) -> bool {
+        policy.resolve(is_terminal, |key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn auto_should_colour_terminals_only() {
+        assert!(resolve(ColourPolicy::Auto, true, &[]));
+        assert!(!resolve(ColourPolicy::Auto, false, &[]));
+    }
+
+    #[test]
+    fn always_and_never_should_ignore_environment() {
+        assert!(resolve(ColourPolicy::Always, false, &[(NO_COLOR, "1")]));
+        assert!(!resolve(ColourPolicy::Never, true, &[(FORCE_COLOR, "1")]));
+    }
+
+    #[test]
+    fn auto_should_respect_no_color_unless_empty() {
+        assert!(!resolve(ColourPolicy::Auto, true, &[(NO_COLOR, "1")]));
+        assert!(resolve(ColourPolicy::Auto, true, &[(NO_COLOR, "")]));
+    }
+
+    #[test]
+    fn auto_should_respect_force_color() {
+        assert!(resolve(ColourPolicy::Auto, false, &[(FORCE_COLOR, "1")]));
+        assert!(resolve(ColourPolicy::Auto, false, &[(FORCE_COLOR, "")]));
+        assert!(!resolve(ColourPolicy::Auto, true, &[(FORCE_COLOR, "0")]));
+        assert!(resolve(ColourPolicy::Auto, false, &[(CLICOLOR_FORCE, "1")]));
+    }
+
+    #[test]
+    fn auto_should_not_colour_dumb_terminals_lambda_or_clicolor_0() {
+        assert!(!resolve(ColourPolicy::Auto, true, &[(TERM, "dumb")]));
+        assert!(!resolve(ColourPolicy::Auto, true, &[(CLICOLOR, "0")]));
+        assert!(!resolve(
+            ColourPolicy::Auto,
+            true,
+            &[(AWS_LAMBDA_ENV_VAR_NAME, "function")]
+        ));
+    }
+
+    #[test]
+    fn auto_should_colour_known_ci_providers() {
+        assert!(resolve(
+            ColourPolicy::Auto,
+            false,
+            &[("GITHUB_ACTIONS", "true")]
+        ));
+        assert!(!resolve(ColourPolicy::Auto, false, &[("CI", "true")]));
+    }
+}